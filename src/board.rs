@@ -15,70 +15,144 @@ impl FilledStruct {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TSpin {
+    None,
+    Mini,
+    Full,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Cell {
     Empty,
-    Filled(Color) 
+    Filled(Color),
+    // pushed up from below rather than placed by a piece
+    Garbage,
 }
+const GARBAGE_COLOR: Color = GRAY;
 pub struct Board {
     height: usize,
     width: usize,
+    // rows at the top that are above the visible matrix (vanish zone)
+    hidden: usize,
     cells: Vec<Cell>
 }
 
 impl Board {
-    pub fn new() -> Self {
-        //cells
+    // `height` is the visible matrix, the vanish zone is added on top
+    pub fn with_size(width: usize, height: usize) -> Self {
+        let height = height + HIDDEN_ROWS;
         Self {
-            height: GRID_H,
-            width: GRID_W,
-            cells: vec![Cell::Empty; GRID_H as usize * GRID_W as usize],
+            height,
+            width,
+            hidden: HIDDEN_ROWS,
+            cells: vec![Cell::Empty; height * width],
         }
     }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    // Total rows, vanish zone included
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn visible_height(&self) -> usize {
+        self.height - self.hidden
+    }
+    // Where a piece's rotation centre spawns: left of centre, in the
+    // bottom row of the vanish zone
+    pub fn spawn_point(&self) -> (i32, i32) {
+        ((self.width as i32 - 1) / 2, self.hidden as i32 - 1)
+    }
     pub fn in_bounds(&self, col: i32, row: i32) -> bool {
         col >= 0 
         && row >= 0 
-        && col < GRID_W as i32 
-        && row < GRID_H as i32 
+        && col < self.width as i32 
+        && row < self.height as i32 
+    }
+    pub fn hidden_rows(&self) -> i32 {
+        self.hidden as i32
+    }
+    pub fn in_vanish_zone(&self, row: i32) -> bool {
+        row < self.hidden as i32
     }
     pub fn lock_piece( &mut self, piece: &Piece) {
         for (col, row) in piece.cells() {
             // we use an vector as a flattened grid - we need to calculate position
-            let idx = (row as usize * GRID_W ) + col as usize;
-            self.cells[idx] = Cell::Filled(piece.kind.color());
+            let idx = self.cell_idx(col, row);
+            self.cells[idx] = Cell::Filled(piece.color());
         }
     }
     pub fn filled_cells(&self) -> Vec<FilledStruct>{
         let mut out = Vec::new(); 
         for (i, cell) in self.cells.iter().enumerate() {
-            if let Cell::Filled(color) = cell {
-                // this cell is filled - calculate grid location
-                let row = (i / self.width ) as i32;
-                let col = (i % self.width) as i32;
-                out.push( FilledStruct::new( col, row, *color ) );
-            }
+            let color = match cell {
+                Cell::Empty => continue,
+                Cell::Filled(color) => *color,
+                Cell::Garbage => GARBAGE_COLOR,
+            };
+            // this cell is filled - calculate grid location
+            let row = (i / self.width ) as i32;
+            let col = (i % self.width) as i32;
+            out.push( FilledStruct::new( col, row, color ) );
         }
         out
     }
     fn cell_idx(&self, col:i32,row: i32) -> usize {
-        (row as usize * GRID_W ) + col as usize
+        (row as usize * self.width ) + col as usize
     }
     pub fn cell_filled(&self, col:i32, row:i32) -> bool {
         self.cells[self.cell_idx(col, row)] != Cell::Empty
     }
-    pub fn piece_surrounded(&self, piece: &Piece ) -> bool {
-        false
+    // Walls and floor count as filled
+    fn is_solid(&self, col:i32, row:i32) -> bool {
+        !self.in_bounds(col, row) || self.cell_filled(col, row)
+    }
+    // Three-corner rule. Assumes the caller has checked the piece is a T and
+    // its last successful action was a rotation. `far_kick` is whether that
+    // rotation used the (1,2) kick, which always counts as a full T-spin.
+    pub fn t_spin(&self, piece: &Piece, far_kick: bool) -> TSpin {
+        // Found from the cells rather than the orientation, since rotation
+        // systems disagree on where the T points and where its centre sits.
+        let cells = piece.cells();
+        let has = |c: i32, r: i32| cells.contains(&(c, r));
+        let Some(&(cc, cr)) = cells.iter()
+            .find(|&&(c, r)| (has(c-1, r) && has(c+1, r)) || (has(c, r-1) && has(c, r+1)))
+        else {
+            return TSpin::None;
+        };
+        // the arm with no opposite is the way the T points
+        let (pc, pr) = [(0,-1),(1,0),(0,1),(-1,0)].into_iter()
+            .find(|&(dc, dr)| has(cc + dc, cr + dr) && !has(cc - dc, cr - dr))
+            .unwrap_or((0,-1));
+        // corners around the T's centre, the two the T points at come first
+        let corners: [(i32, i32); 4] = if pc == 0 {
+            [(-1, pr),(1, pr),(-1,-pr),(1,-pr)]
+        } else {
+            [(pc,-1),(pc, 1),(-pc,-1),(-pc, 1)]
+        };
+        let solid: Vec<bool> = corners.iter()
+            .map(|&(dc, dr)| self.is_solid(cc + dc, cr + dr))
+            .collect();
+        if solid.iter().filter(|&&s| s).count() < 3 {
+            return TSpin::None;
+        }
+        if (solid[0] && solid[1]) || far_kick {
+            TSpin::Full
+        } else {
+            TSpin::Mini
+        }
     }
     pub fn full_rows(&self) -> Vec<i32> {
         // search from bottom of board to top checking for complete lines
         // abort search through coloumns when empty column is found
         let mut out: Vec<i32> = Vec::new();
         // error in here...
-        let row_cnt = GRID_H as i32;
-        let col_cnt = GRID_W as i32;
+        let row_cnt = self.height as i32;
+        let col_cnt = self.width as i32;
         for row in (0..row_cnt).rev() {
             let mut row_filled: bool = true;
-            for col in (0..col_cnt as i32) {
+            for col in 0..col_cnt {
                 if !self.cell_filled(col, row) {
                     row_filled = false;
                     break;
@@ -92,24 +166,29 @@ impl Board {
         out
     }
     
+    // True if clearing the current full rows would leave the board empty
+    pub fn clears_to_empty(&self) -> bool {
+        let filled = self.cells.iter().filter(|c| **c != Cell::Empty).count();
+        filled == self.full_rows().len() * self.width
+    }
     pub fn clear_and_collapse(&mut self) {
         let rows = self.full_rows();
         if rows.is_empty() { return; }
 
-        let mut cleared = vec![false; GRID_H as usize];
+        let mut cleared = vec![false; self.height];
         for row in rows {
-            if (0..GRID_H as i32).contains(&row) {
+            if (0..self.height as i32).contains(&row) {
                 cleared[row as usize] = true;
             }
         }
 
-        let mut write = GRID_H - 1;
-        for read in (0..GRID_H).rev() {
+        let mut write = self.height - 1;
+        for read in (0..self.height).rev() {
             if cleared[read] {
                 continue; // disapear
             }
             if read != write {
-                for col in 0..GRID_W as i32 {
+                for col in 0..self.width as i32 {
                     let src = self.cell_idx(col, read as i32);
                     let dst = self.cell_idx(col, write as i32);
                     self.cells[dst] = self.cells[src];
@@ -117,13 +196,160 @@ impl Board {
             }
             write -= 1;
         } 
-
+        // rows left over at the top are now empty
+        for row in 0..=write {
+            for col in 0..self.width as i32 {
+                let idx = self.cell_idx(col, row as i32);
+                self.cells[idx] = Cell::Empty;
+            }
+        }
+    }
+    // Shifts the whole stack up a row and fills the bottom row with
+    // garbage, leaving `hole` empty. The counterpart of clear_and_collapse.
+    // Returns false if that pushed a filled cell off the top of the board.
+    pub fn push_garbage_row(&mut self, hole: i32) -> bool {
+        let overflow = (0..self.width as i32).any(|col| self.cell_filled(col, 0));
+        self.cells.drain(..self.width);
+        let bottom = (0..self.width as i32).map(|col| {
+            if col == hole { Cell::Empty } else { Cell::Garbage }
+        });
+        self.cells.extend(bottom);
+        !overflow
+    }
+    // Anything in the vanish zone, i.e. above the visible matrix
+    pub fn stack_in_vanish_zone(&self) -> bool {
+        self.cells[..self.hidden * self.width].iter().any(|c| *c != Cell::Empty)
+    }
+    // Rows holding garbage that won't be gone once the full rows clear
+    pub fn garbage_rows_left(&self) -> i32 {
+        let full = self.full_rows();
+        (0..self.height as i32)
+            .filter(|row| !full.contains(row))
+            .filter(|&row| (0..self.width as i32).any(|col| self.cells[self.cell_idx(col, row)] == Cell::Garbage))
+            .count() as i32
     }
     pub fn set_row_color(&mut self, color: Color, row: i32) {
-        for col in (0..GRID_W as i32) {
+        for col in 0..self.width as i32 {
             let idx = self.cell_idx(col, row);
             self.cells[idx] = Cell::Filled(color);
         }
     }
     
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece_kind::PieceKind;
+
+    fn fill(board: &mut Board, col: i32, row: i32) {
+        let idx = board.cell_idx(col, row);
+        board.cells[idx] = Cell::Filled(RED);
+    }
+    fn t_piece(col: i32, row: i32, orientation: u8) -> Piece {
+        Piece { orientation, ..Piece::new(PieceKind::T, row, col) }
+    }
+
+    #[test]
+    fn t_spin_full_with_both_front_corners() {
+        let mut board = Board::with_size(10, 20);
+        let bottom = board.height() as i32 - 1;
+        // T pointing down into a one-wide slot, one back corner covered
+        for col in [3, 5] {
+            fill(&mut board, col, bottom);
+        }
+        fill(&mut board, 3, bottom - 2);
+        assert_eq!(board.t_spin(&t_piece(4, bottom - 1, 2), false), TSpin::Full);
+    }
+
+    #[test]
+    fn t_spin_mini_with_one_front_corner() {
+        let mut board = Board::with_size(10, 20);
+        let bottom = board.height() as i32 - 1;
+        // T pointing up on the floor, the floor gives both back corners
+        fill(&mut board, 3, bottom - 1);
+        assert_eq!(board.t_spin(&t_piece(4, bottom, 0), false), TSpin::Mini);
+    }
+
+    #[test]
+    fn t_spin_far_kick_is_always_full() {
+        let mut board = Board::with_size(10, 20);
+        let bottom = board.height() as i32 - 1;
+        fill(&mut board, 3, bottom - 1);
+        assert_eq!(board.t_spin(&t_piece(4, bottom, 0), true), TSpin::Full);
+    }
+
+    #[test]
+    fn t_spin_needs_three_corners() {
+        let board = Board::with_size(10, 20);
+        let bottom = board.height() as i32 - 1;
+        assert_eq!(board.t_spin(&t_piece(4, bottom, 0), false), TSpin::None);
+        assert_eq!(board.t_spin(&t_piece(4, bottom, 0), true), TSpin::None);
+    }
+
+    #[test]
+    fn t_spin_walls_count_as_corners() {
+        let mut board = Board::with_size(10, 20);
+        let bottom = board.height() as i32 - 1;
+        // T pointing right with its back to the left wall
+        fill(&mut board, 1, bottom - 2);
+        assert_eq!(board.t_spin(&t_piece(0, bottom - 1, 1), false), TSpin::Mini);
+        fill(&mut board, 1, bottom);
+        assert_eq!(board.t_spin(&t_piece(0, bottom - 1, 1), false), TSpin::Full);
+    }
+
+    #[test]
+    fn clears_to_empty_only_when_every_filled_row_is_full() {
+        let mut board = Board::with_size(10, 20);
+        let bottom = board.height() as i32 - 1;
+        board.set_row_color(RED, bottom);
+        board.set_row_color(RED, bottom - 1);
+        assert!(board.clears_to_empty());
+        fill(&mut board, 0, bottom - 2);
+        assert!(!board.clears_to_empty());
+    }
+
+    #[test]
+    fn clears_to_empty_counts_the_vanish_zone() {
+        let mut board = Board::with_size(10, 20);
+        board.set_row_color(RED, board.height() as i32 - 1);
+        fill(&mut board, 4, 0);
+        assert!(!board.clears_to_empty());
+    }
+
+    #[test]
+    fn push_garbage_row_shifts_the_stack_up() {
+        let mut board = Board::with_size(10, 20);
+        let bottom = board.height() as i32 - 1;
+        fill(&mut board, 0, bottom);
+        assert!(board.push_garbage_row(3));
+        assert!(board.cell_filled(0, bottom - 1));
+        assert!(!board.cell_filled(3, bottom));
+        assert_eq!(board.filled_cells().len(), 10);
+        assert_eq!(board.garbage_rows_left(), 1);
+    }
+
+    #[test]
+    fn push_garbage_row_reports_overflow() {
+        let mut board = Board::with_size(10, 20);
+        fill(&mut board, 5, 1);
+        assert!(board.push_garbage_row(0));
+        assert!(board.cell_filled(5, 0));
+        assert!(!board.push_garbage_row(0));
+        assert!(!board.cell_filled(5, 0));
+    }
+
+    #[test]
+    fn garbage_rows_left_skips_rows_about_to_clear() {
+        let mut board = Board::with_size(10, 20);
+        let bottom = board.height() as i32 - 1;
+        board.push_garbage_row(2);
+        board.push_garbage_row(7);
+        assert_eq!(board.garbage_rows_left(), 2);
+        fill(&mut board, 7, bottom);
+        assert_eq!(board.garbage_rows_left(), 1);
+        board.clear_and_collapse();
+        assert_eq!(board.garbage_rows_left(), 1);
+        fill(&mut board, 2, bottom);
+        assert_eq!(board.garbage_rows_left(), 0);
+    }
+}
@@ -1,14 +1,16 @@
 pub const SQUARE_SIZE:f32                   = 30.0;
 pub const LINE_THICKNESS:f32                = 2.0;
-pub const GRID_H: usize                     = 22;
+pub const GRID_H: usize                     = 20; // visible matrix
 pub const GRID_W: usize                     = 10;
-pub const DEFAULT_SPAWN_COL:i32             = 5;
-pub const BOARD_W:f32                       = GRID_W as f32 * SQUARE_SIZE;
-pub const BOARD_H:f32                       = GRID_H as f32 * SQUARE_SIZE;
+pub const HIDDEN_ROWS: usize                = 20; // vanish zone above GRID_H
+pub const PREVIEW_SCALE:f32                 = 0.6;
+pub const PANEL_W:f32                       = 4.0 * SQUARE_SIZE * PREVIEW_SCALE;
+pub const PANEL_MARGIN:f32                  = 20.0;
 pub const SECOND: f32                       = 1000.0;
-pub const FALL_INTERVAL_MS:f32              = 500.0;
-pub const INPUT_INTERVAL_MS:f32             = 100.0;
+pub const FRAME_MS:f32                      = SECOND / 60.0;
+pub const MAX_GRAVITY_MS_PER_ROW:f32        = FRAME_MS / 20.0; // 20G
 pub const LOCK_DELTA_THRESHOLD:f32          = 500.0;
 pub const CLEAR_ROW_FLASH_INTERVAL_MS:f32   = 100.0;
 pub const CLEAR_ROW_INTERVAL_MS:f32         = 500.0;
-pub const PRE_LOCK_MOVES_ALLOWED: u8        = 15;
\ No newline at end of file
+pub const PRE_LOCK_MOVES_ALLOWED: u8        = 15;
+pub const MAX_PREVIEW_COUNT: usize          = 7;
\ No newline at end of file
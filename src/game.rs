@@ -1,9 +1,32 @@
+use std::collections::{BTreeMap, VecDeque};
 use crate::board::*;
 use crate::piece::*;
 use crate::constants::*;
 use macroquad::prelude::*;
 use crate::piece_kind::*;
-use crate::button::*;
+use crate::randomizer::*;
+use crate::rules::*;
+use crate::settings::Settings;
+use crate::scoring::*;
+use crate::mode::*;
+use crate::records::*;
+use crate::garbage::GarbageGenerator;
+use crate::piece_set::PieceSet;
+use macroquad::rand::rand;
+
+const ROTATE_CW_KEYS: [KeyCode; 2]  = [KeyCode::Up, KeyCode::X];
+const ROTATE_CCW_KEYS: [KeyCode; 1] = [KeyCode::Z];
+const ROTATE_180_KEYS: [KeyCode; 1] = [KeyCode::Q];
+const HOLD_KEYS: [KeyCode; 3]       = [KeyCode::LeftShift, KeyCode::RightShift, KeyCode::C];
+
+fn any_pressed(keys: &[KeyCode]) -> bool {
+    keys.iter().any(|&k| is_key_pressed(k))
+}
+// Held from an earlier frame. A key pressed this frame is left to
+// process_key_press so it doesn't act twice.
+fn any_held(keys: &[KeyCode]) -> bool {
+    keys.iter().any(|&k| is_key_down(k) && !is_key_pressed(k))
+}
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 enum Actions {
@@ -11,70 +34,293 @@ enum Actions {
     Moved,
     Rotate,
     SoftDrop,
+    SonicDrop,
     HardDrop,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
-enum ClearResult {
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ClearResult {
     None,
     Single,
     Double,
     Triple,
     Tetris,
+    TSpin,
     TSpinMini,
+    TSpinMiniSingle,
+    TSpinMiniDouble,
     TSpinSingle,
     TSpinDouble,
     TSpinTriple,
+    // all-spins: a non-T piece rotated into a spot it can't move out of
+    Spin,
+    SpinSingle,
+    SpinDouble,
+    SpinTriple,
+}
+impl ClearResult {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClearResult::None            => "",
+            ClearResult::Single          => "Single",
+            ClearResult::Double          => "Double",
+            ClearResult::Triple          => "Triple",
+            ClearResult::Tetris          => "Tetris",
+            ClearResult::TSpin           => "T-Spin",
+            ClearResult::TSpinMini       => "T-Spin Mini",
+            ClearResult::TSpinMiniSingle => "T-Spin Mini Single",
+            ClearResult::TSpinMiniDouble => "T-Spin Mini Double",
+            ClearResult::TSpinSingle     => "T-Spin Single",
+            ClearResult::TSpinDouble     => "T-Spin Double",
+            ClearResult::TSpinTriple     => "T-Spin Triple",
+            ClearResult::Spin            => "Spin",
+            ClearResult::SpinSingle      => "Spin Single",
+            ClearResult::SpinDouble      => "Spin Double",
+            ClearResult::SpinTriple      => "Spin Triple",
+        }
+    }
+    // Variable goal points: the guideline base score / 100
+    pub fn goal_points(&self) -> i32 {
+        match self {
+            ClearResult::None            => 0,
+            ClearResult::Single          => 1,
+            ClearResult::Double          => 3,
+            ClearResult::Triple          => 5,
+            ClearResult::Tetris          => 8,
+            ClearResult::TSpin           => 4,
+            ClearResult::TSpinMini       => 1,
+            ClearResult::TSpinMiniSingle => 2,
+            ClearResult::TSpinMiniDouble => 4,
+            ClearResult::TSpinSingle     => 8,
+            ClearResult::TSpinDouble     => 12,
+            ClearResult::TSpinTriple     => 16,
+            ClearResult::Spin            => 1,
+            ClearResult::SpinSingle      => 2,
+            ClearResult::SpinDouble      => 4,
+            ClearResult::SpinTriple      => 6,
+        }
+    }
+}
+// What the last lock did, for the UI and stats to react to
+#[derive(Copy, Clone, Debug)]
+pub struct ClearEvent {
+    pub result: ClearResult,
+    pub lines: i32,
+    pub perfect_clear: bool,
+    pub back_to_back: bool,
+    pub combo: i32,
+    pub points: i32,
+}
+impl Default for ClearEvent {
+    fn default() -> Self {
+        Self {
+            result: ClearResult::None,
+            lines: 0,
+            perfect_clear: false,
+            back_to_back: false,
+            combo: -1,
+            points: 0,
+        }
+    }
+}
+// Running totals for the results screen
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub pieces: i32,
+    // locks by what they did, ClearResult::None excluded
+    pub clears: BTreeMap<ClearResult, i32>,
+}
+impl Stats {
+    pub fn points_per_piece(&self, score: i32) -> f32 {
+        if self.pieces == 0 {
+            return 0.0;
+        }
+        score as f32 / self.pieces as f32
+    }
+}
+// Guideline names, hence the shared suffix
+#[allow(clippy::enum_variant_names)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TopOut {
+    // the next piece overlaps the stack where it spawns
+    BlockOut,
+    // a piece locked entirely in the vanish zone
+    LockOut,
+    // a piece locked partly in the vanish zone (if the rule set allows it)
+    PartialLockOut,
+    // rising garbage pushed the stack above the visible matrix
+    GarbageOut,
+}
+impl TopOut {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TopOut::BlockOut       => "Block out",
+            TopOut::LockOut        => "Lock out",
+            TopOut::PartialLockOut => "Partial lock out",
+            TopOut::GarbageOut     => "Pushed out by garbage",
+        }
+    }
 }
 pub enum PlayState {
     Start,
+    // handling and display settings, opened from the start menu
+    Settings,
     Playing,
     ClearBlocks,
     Paused,
-    GameOver
+    GameOver,
+    // the mode's goal was reached
+    Complete,
 }
 pub struct GameState {
     // public
     pub play_state: PlayState,
     pub board: Board,
     pub current_piece: Piece,
-    pub next_piece: Piece,
+    pub hold_piece: PieceKind,
+    pub hold_used: bool,
     pub flash_anim_color: Color,
     pub score: i32,
+    // consecutive clearing locks - 1, so -1 is no combo and 0 the first clear
+    pub combo: i32,
+    pub last_clear: ClearEvent,
+    pub perfect_clears: i32,
+    pub stats: Stats,
+    pub top_out: Option<TopOut>,
+    pub rules: RuleSet,
+    // built from rules.pieces
+    pub pieces: PieceSet,
+    // why rules.pieces couldn't be built, in which case `pieces` is the
+    // standard set
+    pub pieces_error: Option<String>,
+    pub settings: Settings,
+    pub mode: GameMode,
+    pub records: Records,
+    // play time, counted from the first input until the goal is reached
+    pub elapsed_ms: f32,
+    // elapsed_ms at every SPRINT_SPLIT_LINES lines
+    pub splits: Vec<f32>,
+    // the personal best as it stood when this run started, for splits to
+    // compare against
+    pub best_sprint: Option<SprintRecord>,
+    pub new_record: bool,
+    // selected start menu row, see exec_start_frame
+    pub menu_row: usize,
+    // selected settings screen row
+    pub settings_row: usize,
     // gamestate
+    randomizer: Box<dyn Randomizer>,
+    garbage: GarbageGenerator,
+    scoring: Box<dyn ScoringSystem>,
+    next_queue: VecDeque<PieceKind>,
     player_interacting: bool,
-    pre_lock_moves:u8,
+    // lock delay bookkeeping, see LockDelayPolicy
+    lock_resets: u8,
+    lowest_row: i32,
+    last_row: i32,
+    touched_down: bool,
     last_action: Actions,
+    last_kick: usize,
+    last_rotation: RotDir,
     level: i32,
-    last_clear_result: ClearResult,
+    // variable goal points towards the next level
+    goal_points: i32,
     back_to_back: bool,
     lines_cleared:i32,
     // timing
+    timer_running: bool,
+    goal_reached: bool,
+    // time since garbage last rose, see GameMode::rise_interval_ms
+    rise_timer: f32,
     fall_timer: f32,
-    input_timer:f32,
+    are_timer: f32,
+    // handling
+    shift_dir: i32,
+    das_timer: f32,
+    arr_timer: f32,
+    das_cut_timer: f32,
+    soft_drop_timer: f32,
     lock_delta: f32,
     clear_row_timer: f32,
     clear_row_flash_timer: f32,    
 }
 impl Default for GameState {
     fn default() -> Self{
+        Self::with_rules(RuleSet::default())
+    }
+
+}
+impl GameState {
+    // A new session, with the player's saved settings and records
+    pub fn new() -> Self {
+        Self { settings: Settings::load(), records: Records::load(), ..Self::default() }
+    }
+    pub fn with_mode(mode: GameMode, rules: RuleSet) -> Self {
+        let mut game = Self { mode, level: mode.start_level(), ..Self::with_rules(rules) };
+        for _ in 0..mode.garbage_rows() {
+            game.push_garbage_row();
+        }
+        game
+    }
+    pub fn with_rules(rules: RuleSet) -> Self {
+        let seed = rules.seed.unwrap_or_else(|| ((rand() as u64) << 32) | rand() as u64);
+        let (pieces, pieces_error) = match rules.pieces.build() {
+            Ok(pieces) => (pieces, None),
+            Err(e) => (PieceSet::standard(), Some(e)),
+        };
         Self {
             play_state: PlayState::Start,            
-            board: Board::new(),
+            board: Board::with_size(rules.board_width, rules.board_height),
             current_piece: Piece::default(),
-            next_piece: Piece::default(),
+            hold_piece: PieceKind::None,
+            hold_used: false,
+            randomizer: rules.randomizer.build(seed, pieces.kinds()),
+            // own stream so garbage doesn't change the piece sequence
+            garbage: GarbageGenerator::new(seed.rotate_left(32)),
+            scoring: rules.scoring.build(),
+            next_queue: VecDeque::new(),
+            rules,
+            pieces,
+            pieces_error,
+            settings: Settings::default(),
+            mode: GameMode::default(),
+            records: Records::default(),
+            elapsed_ms: 0.0,
+            splits: Vec::new(),
+            best_sprint: None,
+            new_record: false,
+            menu_row: 0,
+            settings_row: 0,
             
             player_interacting: false,
-            pre_lock_moves:0,
+            lock_resets: 0,
+            lowest_row: 0,
+            last_row: 0,
+            touched_down: false,
             score:0,
+            combo: -1,
             back_to_back: false,
-            last_clear_result: ClearResult::None,
+            last_clear: ClearEvent::default(),
+            perfect_clears: 0,
+            stats: Stats::default(),
+            top_out: None,
             last_action: Actions::None,
+            last_kick: 0,
+            last_rotation: RotDir::CW,
             level:1,
+            goal_points: 0,
             lines_cleared: 0,
 
+            timer_running: false,
+            goal_reached: false,
+            rise_timer: 0.0,
             fall_timer: 0.0,
-            input_timer: 0.0,
+            are_timer: 0.0,
+            shift_dir: 0,
+            das_timer: 0.0,
+            arr_timer: 0.0,
+            das_cut_timer: 0.0,
+            soft_drop_timer: 0.0,
             lock_delta: 0.0,
             clear_row_timer: 0.0,
             clear_row_flash_timer: 0.0,
@@ -82,12 +328,6 @@ impl Default for GameState {
         }
     }
 
-}
-impl GameState {
-    pub fn new() -> Self {
-        Self::default()
-    }
-
 // ======================================
 // Main Update Loop
 // ======================================
@@ -100,6 +340,9 @@ impl GameState {
             PlayState::Start => {
                 self.exec_start_frame();
             },
+            PlayState::Settings => {
+                self.exec_settings_frame();
+            },
             PlayState::Playing => {
                 self.exec_playing_frame(dt);
             },
@@ -109,11 +352,21 @@ impl GameState {
             PlayState::Paused => {
                 self.exec_paused_frame();
             },
-            PlayState::GameOver => {
+            PlayState::GameOver | PlayState::Complete => {
                 self.exec_gameover_frame();
             }
         }
     }
+    pub fn lines_cleared(&self) -> i32 {
+        self.lines_cleared
+    }
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+    // Goal points still needed for the next level, with a variable goal
+    pub fn goal_remaining(&self) -> Option<i32> {
+        self.mode.variable_goal().then(|| self.level_goal() - self.goal_points)
+    }
     // ===================================================
     // Pause
     // ===================================================
@@ -129,91 +382,299 @@ impl GameState {
     // ===================================================
     fn exec_gameover_frame(&mut self) {
         if is_key_pressed(KeyCode::Enter) {
-            *self = Self::default();
-            self.play_state = PlayState::Start;
+            self.restart(self.mode);
         }
     }
+    // Fresh game back at the start menu, keeping the player's rules,
+    // settings and records
+    fn restart(&mut self, mode: GameMode) {
+        let mut next = Self::with_mode(mode, self.rules.clone());
+        next.settings = self.settings.clone();
+        next.records = std::mem::take(&mut self.records);
+        next.menu_row = self.menu_row;
+        *self = next;
+    }
 
     // ===================================================
     //Start Menu
     // ===================================================
     fn exec_start_frame(&mut self) {
+        // Up/Down picks a menu row, Left/Right changes it. Row 0 is the
+        // mode, then come the mode's options and then the rules.
+        let mode_rows = self.mode.options().len() + 1;
+        let rows = mode_rows + self.rules.options().len();
+        if is_key_pressed(KeyCode::Up) {
+            self.menu_row = (self.menu_row + rows - 1) % rows;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.menu_row = (self.menu_row + 1) % rows;
+        }
+        let dir = if is_key_pressed(KeyCode::Left) {
+            -1
+        } else if is_key_pressed(KeyCode::Right) {
+            1
+        } else {
+            0
+        };
+        if dir != 0 {
+            let mode = match self.menu_row {
+                0 => self.mode.cycle(dir),
+                row if row < mode_rows => self.mode.adjust(row - 1, dir),
+                row => {
+                    self.rules = self.rules.adjust(row - mode_rows, dir);
+                    self.mode
+                },
+            };
+            self.restart(mode);
+            // a new mode may have fewer options
+            let rows = self.mode.options().len() + 1 + self.rules.options().len();
+            if self.menu_row >= rows {
+                self.menu_row = 0;
+            }
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            self.play_state = PlayState::Settings;
+            return;
+        }
         if is_key_pressed(KeyCode::Enter) {
+            if let GameMode::Sprint { lines } = self.mode {
+                self.best_sprint = self.records.sprint_best(lines).cloned();
+            }
             // init game
-            self.next_piece = self.get_next_piece();
+            self.fill_next_queue();
             self.play_state = PlayState::Playing;
         }
     }
     
+    // ===================================================
+    // Settings
+    // ===================================================
+    fn exec_settings_frame(&mut self) {
+        let rows = self.settings.options().len();
+        if is_key_pressed(KeyCode::Up) {
+            self.settings_row = (self.settings_row + rows - 1) % rows;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.settings_row = (self.settings_row + 1) % rows;
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.settings = self.settings.adjust(self.settings_row, -1);
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.settings = self.settings.adjust(self.settings_row, 1);
+        }
+        if is_key_pressed(KeyCode::Tab) || is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::Enter) {
+            self.settings.save();
+            self.play_state = PlayState::Start;
+        }
+    }
+
     // ===================================================
     //Playing
     // ===================================================
     fn exec_playing_frame(&mut self, dt: f32) {
+        // the clock starts with the player's first input
+        if !self.timer_running && !self.goal_reached && get_last_key_pressed().is_some() {
+            self.timer_running = true;
+        }
+        self.tick_timer(dt);
+        // runs down whether or not a direction is held
+        self.das_cut_timer = (self.das_cut_timer - dt).max(0.0);
+        if self.goal_reached {
+            self.play_state = PlayState::Complete;
+            return;
+        }
+        if self.timer_running {
+            self.try_rise_garbage(dt);
+            if !matches!(self.play_state, PlayState::Playing) {
+                return;
+            }
+        }
         if self.current_piece.kind == PieceKind::None {
-            self.spawn_next_piece_piece();
+            // Entry delay (ARE) before the next piece appears
+            if self.are_timer > 0.0 {
+                self.are_timer -= dt;
+                return;
+            }
+            self.spawn_entry_piece();
+            if !matches!(self.play_state, PlayState::Playing) {
+                return;
+            }
         }
         self.handle_input_playing(dt);
+        // hard drop locks during input
+        if self.current_piece.kind == PieceKind::None {
+            return;
+        }
+        // the first piece waits for the clock, so it can't fall or lock
+        // in time that isn't counted
+        if !self.timer_running {
+            return;
+        }
         self.try_drop_current_piece(dt);
         self.try_piece_lock(dt);
-        self.try_clear_lines(); 
     }
     fn handle_input_playing(&mut self, delta: f32) {
-        self.player_interacting = false;
         let press_action = self.process_key_press();
+        // a hard drop already locked the piece, nothing left to move
+        if self.current_piece.kind == PieceKind::None {
+            return;
+        }
         let hold_action  = self.process_key_hold(delta);
 
         // Decide which action “wins” for this frame
         let new_state = self.prioritize_actions(press_action, hold_action);
-        if new_state != Actions::None {
+        // only successful moves and rotations count towards lock resets
+        self.player_interacting = new_state != Actions::None;
+        if self.player_interacting {
             self.last_action = new_state;
-            self.player_interacting = true;
         }
     }
     fn process_key_hold(&mut self, delta: f32) -> Actions {
-        self.input_timer += delta;
-        if self.input_timer < INPUT_INTERVAL_MS {
-            return Actions::None;
+        // Only successful moves count, a blocked move must not
+        // overwrite a rotation for T-spin purposes
+        let moved = self.process_shift(delta);
+        let dropped = self.process_soft_drop(delta);
+        if dropped {
+            Actions::SoftDrop
+        } else if moved {
+            Actions::Moved
+        } else {
+            Actions::None
+        }
+    }
+    // Left/Right with Delayed Auto Shift and Auto Repeat Rate.
+    // The most recently pressed direction wins while both are held.
+    fn process_shift(&mut self, delta: f32) -> bool {
+        let left_down  = is_key_down(KeyCode::Left) || is_key_down(KeyCode::A);
+        let right_down = is_key_down(KeyCode::Right) || is_key_down(KeyCode::D);
+        let mut moved = false;
+        let mut tapped = false;
+        if is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::A) {
+            self.start_shift(-1);
+            moved |= self.shift(-1);
+            tapped = true;
+        }
+        if is_key_pressed(KeyCode::Right) || is_key_pressed(KeyCode::D) {
+            self.start_shift(1);
+            moved |= self.shift(1);
+            tapped = true;
         }
-        self.input_timer -= INPUT_INTERVAL_MS;
-        if is_key_down(KeyCode::Left) || is_key_down(KeyCode::A) {
-            self.move_left();
-            return Actions::Moved;
+        // Released the active direction: fall back to the other if still held.
+        // Also picks up a direction pressed while there was no piece (ARE).
+        let released = (self.shift_dir == -1 && !left_down) || (self.shift_dir == 1 && !right_down);
+        if released || self.shift_dir == 0 {
+            let other = if left_down { -1 } else if right_down { 1 } else { 0 };
+            self.start_shift(other);
         }
-        if is_key_down(KeyCode::Right) || is_key_down(KeyCode::D) {
-            self.move_right();
-            return Actions::Moved;
+        if self.shift_dir == 0 || tapped {
+            return moved;
         }
-        if is_key_down(KeyCode::Down) || is_key_down(KeyCode::S) {
-            self.soft_drop();
-            return Actions::SoftDrop;
+        if self.das_cut_timer > 0.0 {
+            return moved;
         }
-        return Actions::None;
+        let das = self.settings.das_ms;
+        let arr = self.settings.arr_ms;
+        if self.das_timer < das {
+            self.das_timer += delta;
+            if self.das_timer < das {
+                return moved;
+            }
+            // DAS charged: first auto shift now, then one every ARR
+            moved |= self.shift(self.shift_dir);
+            self.arr_timer = 0.0;
+        } else {
+            self.arr_timer += delta;
+        }
+        if arr <= 0.0 {
+            // ARR 0: straight to the wall
+            while self.shift(self.shift_dir) {
+                moved = true;
+            }
+            return moved;
+        }
+        while self.arr_timer >= arr {
+            self.arr_timer -= arr;
+            if !self.shift(self.shift_dir) {
+                self.arr_timer = 0.0;
+                break;
+            }
+            moved = true;
+        }
+        moved
+    }
+    fn start_shift(&mut self, dir: i32) {
+        self.shift_dir = dir;
+        self.das_timer = 0.0;
+        self.arr_timer = 0.0;
+    }
+    // Soft drop falls at gravity * soft drop factor
+    fn process_soft_drop(&mut self, delta: f32) -> bool {
+        if !(is_key_down(KeyCode::Down) || is_key_down(KeyCode::S)) {
+            self.soft_drop_timer = 0.0;
+            return false;
+        }
+        let interval = (self.rules.gravity.ms_per_row( self.level ) / self.settings.soft_drop_factor)
+            .max(MAX_GRAVITY_MS_PER_ROW);
+        let mut dropped = false;
+        if is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::S) {
+            // first row is immediate
+            dropped = self.soft_drop();
+            self.soft_drop_timer = 0.0;
+        } else {
+            self.soft_drop_timer += delta;
+        }
+        while self.soft_drop_timer >= interval {
+            self.soft_drop_timer -= interval;
+            if !self.soft_drop() {
+                self.soft_drop_timer = 0.0;
+                break;
+            }
+            dropped = true;
+        }
+        dropped
     }
 
+    // Every key pressed this frame is handled, in order, since a press is
+    // only seen for one frame. Returns the highest priority action.
     fn process_key_press(&mut self) -> Actions {
-        // Rotate CW
-        if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::X) {
-            self.player_interacting = true;
-            if self.current_piece.try_rotate( RotDir::CW, &self.board ) {
-                return Actions::Rotate;
-            }
+        let mut action = Actions::None;
+        // Rotate CW, CCW or 180, whichever turns first
+        let rotated = (any_pressed(&ROTATE_CW_KEYS) && self.rotate( RotDir::CW ))
+            || (any_pressed(&ROTATE_CCW_KEYS) && self.rotate( RotDir::CCW ))
+            || (any_pressed(&ROTATE_180_KEYS) && self.rotate( RotDir::Half ));
+        if rotated {
+            // a drop this frame locks before handle_input records the action
+            self.last_action = Actions::Rotate;
+            action = Actions::Rotate;
         }
-        // Rotate CCW
-        if is_key_pressed(KeyCode::Z) {
-            self.player_interacting = true;
-            if self.current_piece.try_rotate(RotDir::CCW, &self.board ) {
-                return Actions::Rotate;
+        // Hold
+        if any_pressed(&HOLD_KEYS) {
+            self.try_hold();
+            // the swapped in piece may not fit
+            if self.top_out.is_some() {
+                return Actions::None;
             }
         }
         // HardDrop
         if is_key_pressed(KeyCode::Space) {
-            self.player_interacting = true;
-            // Todo: Hard drop
+            self.hard_drop();
+            action = Actions::HardDrop;
+        // SonicDrop
+        } else if is_key_pressed(KeyCode::W) && self.sonic_drop() {
+            action = Actions::SonicDrop;
+        }
+        // Toggle ghost piece
+        if is_key_pressed(KeyCode::G) {
+            self.settings.show_ghost = !self.settings.show_ghost;
+            self.settings.save();
         }
-        if is_key_pressed(KeyCode::Enter) ||  is_key_pressed(KeyCode::Escape) {
+        // a hard drop may have ended the game
+        let pause = is_key_pressed(KeyCode::Enter) ||  is_key_pressed(KeyCode::Escape);
+        if pause && matches!(self.play_state, PlayState::Playing) {
             self.play_state = PlayState::Paused;
         }
-        return Actions::None;
+        action
     }
     fn prioritize_actions(&self, press: Actions, hold: Actions) -> Actions {
     use Actions::*;
@@ -222,6 +683,9 @@ impl GameState {
         // Highest: Hard drop (if it happened at all)
         (HardDrop, _) => HardDrop,
 
+        // Then a sonic drop, which leaves the piece unlocked
+        (SonicDrop, _) => SonicDrop,
+
         // Then any held soft drop
         (_, SoftDrop) => SoftDrop,
 
@@ -238,31 +702,84 @@ impl GameState {
 
 
 
-    fn soft_drop(&mut self) {
+    fn soft_drop(&mut self) -> bool {
         if self.current_piece.try_move_piece( 0, 1, &self.board ) {
-            // SCORE: +1 Points for soft drop
-            self.update_score(1);
+            let points = self.scoring.dropped( DropKind::Soft, 1 );
+            self.update_score(points);
+            return true;
         }
+        false
     }
-    fn move_right(&mut self) {
-        //move right
-        self.current_piece.try_move_piece( 1, 0, &self.board );
-        self.player_interacting = true;
+    fn hard_drop(&mut self) {
+        let rows = self.current_piece.drop_distance( &self.board );
+        self.current_piece.row += rows;
+        let points = self.scoring.dropped( DropKind::Hard, rows );
+        self.update_score(points);
+        // Locks before handle_input records the action, and a drop of zero
+        // rows keeps the last rotation for T-spin purposes
+        if rows > 0 {
+            self.last_action = Actions::HardDrop;
+        }
+        self.lock_current_piece();
     }
-    fn move_left(&mut self) {
-        //move left
-        self.current_piece.try_move_piece( -1, 0, &self.board );
-        self.player_interacting = true;
+    // Same move as a hard drop but leaves the piece to the lock delay.
+    // Returns false if the piece was already grounded.
+    fn sonic_drop(&mut self) -> bool {
+        let rows = self.current_piece.drop_distance( &self.board );
+        self.current_piece.row += rows;
+        let points = self.scoring.dropped( DropKind::Sonic, rows );
+        self.update_score(points);
+        rows > 0
+    }
+    fn rotate(&mut self, dir: RotDir) -> bool {
+        if dir == RotDir::Half && !self.rules.rotate_180_enabled {
+            return false;
+        }
+        match self.current_piece.try_rotate( dir, &self.board ) {
+            Some(kick) => {
+                self.last_kick = kick;
+                self.last_rotation = dir;
+                self.das_cut_timer = self.settings.das_cut_ms;
+                true
+            },
+            None => false,
+        }
+    }
+    // dir: -1 left, 1 right
+    fn shift(&mut self, dir: i32) -> bool {
+        self.current_piece.try_move_piece( dir, 0, &self.board )
+    }
+    fn try_hold(&mut self) {
+        if !self.rules.hold_enabled || self.hold_used {
+            return;
+        }
+        self.swap_hold( self.current_piece.kind, None );
+    }
+    // Puts `kind` in hold and spawns what was held, or the next piece if
+    // hold was empty. The held piece comes back at the spawn position.
+    fn swap_hold(&mut self, kind: PieceKind, rotation: Option<RotDir>) {
+        let held = std::mem::replace(&mut self.hold_piece, kind);
+        self.hold_used = true;
+        if held == PieceKind::None {
+            self.spawn_next_piece_piece( rotation );
+        } else {
+            self.spawn_piece( held, rotation );
+        }
     }
     fn try_drop_current_piece(&mut self, delta: f32) {
-        // Move block
+        // Move block, possibly several rows in one frame at high gravity
+        let interval = self.rules.gravity.ms_per_row( self.level );
         self.fall_timer += delta;
-        if self.fall_timer < FALL_INTERVAL_MS {
-            return
+        while self.fall_timer >= interval {
+            self.fall_timer -= interval;
+            if !self.current_piece.try_move_piece( 0, 1, &self.board ) {
+                // grounded - don't bank time for after it is moved off a ledge
+                self.fall_timer = 0.0;
+                break;
+            }
+            // falling after a rotation means it was not spun into place
+            self.last_action = Actions::None;
         }
-        self.fall_timer -= FALL_INTERVAL_MS;    
-        self.clear_lock_timer();
-        self.current_piece.try_move_piece( 0, 1, &self.board );
     }
 
     fn update_score(&mut self, points: i32) {
@@ -271,102 +788,327 @@ impl GameState {
     fn clear_lock_timer(&mut self) {
         self.lock_delta = 0.0;
     }
+    fn reset_lock_state(&mut self) {
+        self.clear_lock_timer();
+        self.lock_resets = 0;
+        self.lowest_row = self.current_piece.row;
+        self.last_row = self.current_piece.row;
+        self.touched_down = false;
+    }
     fn try_piece_lock(&mut self, delta: f32) {
-        // is grounded?
+        let row = self.current_piece.row;
+        let stepped = row > self.last_row;
+        self.last_row = row;
+        // Reaching a new lowest row starts the lock delay over
+        if row > self.lowest_row {
+            self.lowest_row = row;
+            self.lock_resets = 0;
+            self.touched_down = false;
+            self.clear_lock_timer();
+        }
+        match self.rules.lock_delay {
+            LockDelayPolicy::Infinite => return,
+            LockDelayPolicy::StepReset => {
+                if stepped {
+                    self.clear_lock_timer();
+                }
+            },
+            LockDelayPolicy::MoveReset { max_resets } => {
+                // only moves made after touching down on this row are counted
+                if self.player_interacting && self.touched_down && self.lock_resets < max_resets {
+                    self.lock_resets += 1;
+                    self.clear_lock_timer();
+                }
+            },
+            LockDelayPolicy::NoReset => {},
+        }
+        // is grounded? timer holds while the piece is in the air
         if self.current_piece.can_move(0, 1, &self.board) {
             return;
         }
         //Invariant: Piece has Lock pending
-        // current_piecely interacting? Allow 15 movements before locking
-        if self.player_interacting && self.pre_lock_moves < PRE_LOCK_MOVES_ALLOWED {
-            self.pre_lock_moves += 1;
-            self.clear_lock_timer();
-            return;
-        }
+        self.touched_down = true;
+        // Out of resets: lock as soon as it lands
+        let out_of_resets = matches!(
+            self.rules.lock_delay,
+            LockDelayPolicy::MoveReset { max_resets } if self.lock_resets >= max_resets
+        );
         // Lock piece
         self.lock_delta += delta;
-        if self.lock_delta > LOCK_DELTA_THRESHOLD {
-            // lock! Save to board and clear current_piece piece
-            self.pre_lock_moves = 0;
-            self.board.lock_piece( &self.current_piece );
-            self.current_piece = Piece::default();
+        if out_of_resets || self.lock_delta > self.rules.lock_delay_ms {
+            self.lock_current_piece();
+        }
+    }
+    fn lock_current_piece(&mut self) {
+        // lock! Save to board and clear current_piece piece
+        self.clear_lock_timer();
+        let cells = self.current_piece.cells();
+        let hidden_cells = cells.iter().filter(|&&(_, row)| self.board.in_vanish_zone(row)).count();
+        let all_spin = self.rules.all_spin
+            && self.last_action == Actions::Rotate
+            && matches!(
+                self.current_piece.kind
+                , PieceKind::S | PieceKind::Z | PieceKind::L | PieceKind::J | PieceKind::I )
+            && self.current_piece.is_immobile( &self.board );
+        self.board.lock_piece( &self.current_piece );
+        // score while current_piece is still the piece that locked
+        self.try_clear_lines( all_spin );
+        self.are_timer = if self.last_clear.lines > 0 {
+            self.rules.line_clear_are_ms
+        } else {
+            self.rules.are_ms
+        };
+        if self.goal_reached {
+            // finished with this lock, nothing left to top out
+        } else if hidden_cells == cells.len() {
+            self.top_out(TopOut::LockOut);
+        } else if hidden_cells > 0 && self.rules.partial_lock_out && self.last_clear.lines == 0 {
+            // a line clear would pull the piece back down into view
+            self.top_out(TopOut::PartialLockOut);
+        }
+        self.current_piece = Piece::default();
+        self.hold_used = false;
+    }
+    // Upcoming pieces, front of the queue spawns next
+    pub fn next_queue(&self) -> &VecDeque<PieceKind> {
+        &self.next_queue
+    }
+    fn fill_next_queue(&mut self) {
+        let len = self.rules.preview_count.clamp(1, MAX_PREVIEW_COUNT);
+        while self.next_queue.len() < len {
+            let kind = self.randomizer.next();
+            self.next_queue.push_back(kind);
+        }
+    }
+    fn take_next_piece(&mut self) -> PieceKind {
+        self.fill_next_queue();
+        let kind = self.next_queue.pop_front().unwrap_or(PieceKind::None);
+        self.fill_next_queue();
+        kind
+    }
+    fn spawn_next_piece_piece(&mut self, rotation: Option<RotDir>) {
+        let kind = self.take_next_piece();
+        self.spawn_piece( kind, rotation );
+    }
+    // `rotation` is applied in the spawn row, before the piece has to fit
+    fn spawn_piece(&mut self, kind: PieceKind, rotation: Option<RotDir>) {
+        self.current_piece = Piece::spawn( kind, &self.board, self.rules.rotation, &self.pieces );
+        if let Some(dir) = rotation {
+            self.rotate( dir );
+        }
+        if !self.current_piece.can_move( 0,0, &self.board) {
+            self.top_out(TopOut::BlockOut);
+            return;
+        }
+        // Guideline: drop one row straight away if there is room
+        self.current_piece.try_move_piece( 0, 1, &self.board );
+        self.fall_timer = 0.0;
+        self.reset_lock_state();
+        // spawned rotated, not spun into place
+        self.last_action = Actions::None;
+    }
+    // The next piece appears after the entry delay. IHS/IRS: hold or
+    // rotation keys held through the delay act on it as it spawns, so a
+    // spawn that only fits rotated or swapped for the held piece is no
+    // block out.
+    fn spawn_entry_piece(&mut self) {
+        let rotation = if any_held(&ROTATE_CW_KEYS) {
+            Some(RotDir::CW)
+        } else if any_held(&ROTATE_CCW_KEYS) {
+            Some(RotDir::CCW)
+        } else if any_held(&ROTATE_180_KEYS) {
+            Some(RotDir::Half)
+        } else {
+            None
+        };
+        if any_held(&HOLD_KEYS) && self.rules.hold_enabled && !self.hold_used {
+            let next = self.take_next_piece();
+            self.swap_hold( next, rotation );
+        } else {
+            self.spawn_next_piece_piece( rotation );
+        }
+    }
+    // Returns false if the stack was pushed off the top of the board
+    fn push_garbage_row(&mut self) -> bool {
+        let hole = self.garbage.next_hole(self.board.width(), self.mode.messiness());
+        self.board.push_garbage_row(hole)
+    }
+    fn try_rise_garbage(&mut self, dt: f32) {
+        let Some(interval) = self.mode.rise_interval_ms(self.elapsed_ms) else {
+            return;
+        };
+        self.rise_timer += dt;
+        if self.rise_timer < interval {
+            return;
+        }
+        self.rise_timer = 0.0;
+        let kept = self.push_garbage_row();
+        if !kept || self.board.stack_in_vanish_zone() {
+            self.top_out(TopOut::GarbageOut);
+            return;
+        }
+        // the falling piece rides up with the stack rather than overlap it,
+        // as far as it takes to fit
+        if self.current_piece.kind == PieceKind::None || self.current_piece.can_move(0, 0, &self.board) {
+            return;
+        }
+        let height = self.board.height() as i32;
+        match (1..=height).find(|&rows| self.current_piece.can_move(0, -rows, &self.board)) {
+            Some(rows) => {
+                self.current_piece.row -= rows;
+                // lands on a new surface, so gets a fresh lock delay
+                self.reset_lock_state();
+            },
+            None => self.top_out(TopOut::GarbageOut),
+        }
+    }
+    fn tick_timer(&mut self, dt: f32) {
+        if !self.timer_running {
+            return;
+        }
+        self.elapsed_ms += dt;
+        let limit = self.mode.time_limit_ms().unwrap_or(f32::INFINITY);
+        if self.elapsed_ms >= limit {
+            self.elapsed_ms = limit;
+            self.reach_goal();
+        }
+    }
+    // Fixed goal: a level every 10 lines from the start level. Variable
+    // goal: a level every 5 x level goal points, awarded by clear type.
+    fn update_level(&mut self, clear: &ClearEvent) {
+        if self.mode.variable_goal() {
+            let mut points = clear.result.goal_points();
+            if clear.back_to_back {
+                points += points / 2;
+            }
+            self.goal_points += points;
+            while self.goal_points >= self.level_goal() {
+                self.goal_points -= self.level_goal();
+                self.level += 1;
+            }
+        } else {
+            self.level = self.mode.start_level() + self.lines_cleared / 10;
+        }
+        if let Some(max) = self.mode.max_level() {
+            self.level = self.level.min(max);
         }
     }
-    fn get_next_piece(&mut self) -> Piece {
-        let mut piece = Piece::random_piece();
-        piece.try_kick( &self.board );
-        piece
+    fn level_goal(&self) -> i32 {
+        5 * self.level
     }
-    fn spawn_next_piece_piece(&mut self) {
-        self.current_piece = self.next_piece;
-        self.next_piece = self.get_next_piece();
-        if !self.next_piece.can_move( 0,0, &self.board) {
-            self.play_state = PlayState::GameOver
+    // Stops the clock and records the run. The game ends once the clear
+    // animation finishes.
+    fn reach_goal(&mut self) {
+        self.timer_running = false;
+        self.goal_reached = true;
+        if let GameMode::Sprint { lines } = self.mode {
+            self.new_record = self.records.submit_sprint(SprintRecord {
+                lines,
+                time_ms: self.elapsed_ms,
+                splits: self.splits.clone(),
+            });
+            if self.new_record {
+                self.records.save();
+            }
         }
     }
-    fn try_clear_lines(&mut self) {
+    fn top_out(&mut self, reason: TopOut) {
+        info!( "top out: {}", reason.describe() );
+        self.top_out = Some(reason);
+        self.play_state = PlayState::GameOver;
+    }
+    // `all_spin` is whether the piece was spun in, see RuleSet::all_spin
+    fn try_clear_lines(&mut self, all_spin: bool) {
         let full_rows = self.board.full_rows();
         let line_cnt = full_rows.len() as i32;
+        let perfect_clear = line_cnt > 0 && self.board.clears_to_empty();
+        let lines_before = self.lines_cleared;
         self.lines_cleared += line_cnt;
+        for _ in lines_before / SPRINT_SPLIT_LINES..self.lines_cleared / SPRINT_SPLIT_LINES {
+            self.splits.push(self.elapsed_ms);
+        }
+        let dug_out = self.mode.garbage_rows() > 0 && self.board.garbage_rows_left() == 0;
+        if dug_out || self.mode.line_goal().is_some_and(|goal| self.lines_cleared >= goal) {
+            self.reach_goal();
+        }
         // calculate score
-        let t_spin = self.last_action == Actions::Rotate && self.board.piece_surrounded(&self.current_piece);
-        let clear_result = if t_spin {
-            match line_cnt {
-                0 => ClearResult::TSpinMini,
-                1 => ClearResult::TSpinSingle,
-                2 => ClearResult::TSpinDouble,
-                3 => ClearResult::TSpinTriple,
-                _ => ClearResult::None, // This should never happen unless t_spin calc is broken.
-            }
+        let t_spin = if self.last_action == Actions::Rotate && self.current_piece.kind == PieceKind::T {
+            // the far kick promotion only applies to quarter turns
+            let far_kick = self.last_rotation != RotDir::Half && self.last_kick == SRS_FAR_KICK;
+            self.board.t_spin(&self.current_piece, far_kick)
         } else {
-            match line_cnt {
-                1 => ClearResult::Single,
-                2 => ClearResult::Double,
-                3 => ClearResult::Triple,
-                4 => ClearResult::Tetris,
-                _ => ClearResult::None,
-            }
+            TSpin::None
         };
-        let base_points = match clear_result {
-            ClearResult::Single        => 100,
-            ClearResult::Double        => 300,
-            ClearResult::Triple        => 500,
-            ClearResult::Tetris        => 800,
-            ClearResult::TSpinMini     => 100,   // or 0, depending on your rules
-            ClearResult::TSpinSingle   => 800,
-            ClearResult::TSpinDouble   => 1200,
-            ClearResult::TSpinTriple   => 1600,
-            ClearResult::None          => 0,
+        let clear_result = match (t_spin, line_cnt) {
+            (TSpin::None, 0) if all_spin => ClearResult::Spin,
+            (TSpin::None, 1) if all_spin => ClearResult::SpinSingle,
+            (TSpin::None, 2) if all_spin => ClearResult::SpinDouble,
+            (TSpin::None, 3) if all_spin => ClearResult::SpinTriple,
+            (TSpin::Full, 0) => ClearResult::TSpin,
+            (TSpin::Full, 1) => ClearResult::TSpinSingle,
+            (TSpin::Full, 2) => ClearResult::TSpinDouble,
+            (TSpin::Full, 3) => ClearResult::TSpinTriple,
+            (TSpin::Mini, 0) => ClearResult::TSpinMini,
+            (TSpin::Mini, 1) => ClearResult::TSpinMiniSingle,
+            (TSpin::Mini, 2) => ClearResult::TSpinMiniDouble,
+            (_, 1) => ClearResult::Single,
+            (_, 2) => ClearResult::Double,
+            (_, 3) => ClearResult::Triple,
+            (_, 4) => ClearResult::Tetris,
+            _ => ClearResult::None,
         };
-        let mut score = base_points * self.level;
-        // No lines cleared
         // Back-to-back mode when:
-        // * Tetris, or T-Spin happens
-        // * Future Tetris or T-Spin will score +50%
+        // * Tetris, T-Spin or all-spin line clear happens
+        // * Future Tetris or T-Spin line clear scores more, if the
+        //   scoring system rewards it
+        // * Only a Single/Double/Triple breaks it, locks without lines don't
         let is_b2b = matches!(
             clear_result 
             , ClearResult::Tetris
+                | ClearResult::TSpinMiniSingle
+                | ClearResult::TSpinMiniDouble
                 | ClearResult::TSpinSingle
                 | ClearResult::TSpinDouble
-                | ClearResult::TSpinTriple ); 
+                | ClearResult::TSpinTriple
+                | ClearResult::SpinSingle
+                | ClearResult::SpinDouble
+                | ClearResult::SpinTriple ); 
+        let was_b2b = self.back_to_back;
         if is_b2b {
-            if self.back_to_back {
-                score += score / 2;
-
-            } else {
-                self.back_to_back = true;
-            }
+            self.back_to_back = true;
         } 
-        else 
+        else if line_cnt > 0
         {
             self.back_to_back = false;
         }
+        // consecutive clearing locks
+        if line_cnt > 0 {
+            self.combo += 1;
+        } else {
+            self.combo = -1;
+        }
+        if perfect_clear {
+            self.perfect_clears += 1;
+        }
+        let mut event = ClearEvent {
+            result: clear_result,
+            lines: line_cnt,
+            perfect_clear,
+            back_to_back: was_b2b && is_b2b,
+            combo: self.combo,
+            points: 0,
+        };
+        event.points = self.scoring.lock( &event, self.level );
+        self.stats.pieces += 1;
+        if clear_result != ClearResult::None {
+            *self.stats.clears.entry(clear_result).or_insert(0) += 1;
+        }
+        let score = event.points;
         // update score
         self.update_score( score );
         // set previous
-        self.last_clear_result = clear_result;
-        // Update level
-        self.level = self.lines_cleared / 10 + 1;
+        self.last_clear = event;
+        self.update_level( &event );
         if line_cnt > 0 {
             info!( "level:[{}], lines_cleared:[{}], score:[{}]", self.level, self.lines_cleared, score );
             self.play_state = PlayState::ClearBlocks;
@@ -377,6 +1119,7 @@ impl GameState {
     // Clear Blocks
     // ===================================================
     fn exec_clearblock_frame(&mut self, dt: f32) {
+        self.tick_timer(dt);
         self.handle_flash_animation(dt);
         self.try_clear_full_rows(dt);
     }
@@ -400,12 +1143,170 @@ impl GameState {
         if self.clear_row_timer > CLEAR_ROW_INTERVAL_MS {
             self.clear_row_timer = 0.0;
             self.board.clear_and_collapse();
-            // Can we 
             // clear animation flags
-            self.clear_row_timer = 0.0;
             self.flash_anim_color = WHITE;
-            self.play_state = PlayState::Playing;
+            self.play_state = if self.goal_reached {
+                PlayState::Complete
+            } else {
+                PlayState::Playing
+            };
         }
     }
     
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> GameState {
+        let rules = RuleSet { seed: Some(1), ..RuleSet::default() };
+        let mut game = GameState::with_rules(rules);
+        game.play_state = PlayState::Playing;
+        game
+    }
+    // An O in the spawn row, blocking the left of a flat I
+    fn block_spawn(game: &mut GameState) {
+        let (col, row) = game.board.spawn_point();
+        game.board.lock_piece(&Piece::new(PieceKind::O, row, col - 2));
+    }
+
+    #[test]
+    fn blocked_spawn_tops_out() {
+        let mut game = game();
+        block_spawn(&mut game);
+        game.spawn_piece(PieceKind::I, None);
+        assert_eq!(game.top_out, Some(TopOut::BlockOut));
+    }
+
+    #[test]
+    fn initial_rotation_applies_before_block_out() {
+        let mut game = game();
+        block_spawn(&mut game);
+        game.spawn_piece(PieceKind::I, Some(RotDir::CW));
+        assert_eq!(game.top_out, None);
+        assert_eq!(game.current_piece.orientation, 1);
+        // turned in the spawn row, then dropped a row
+        let (_, row) = game.board.spawn_point();
+        assert_eq!(game.current_piece.row, row + 1);
+    }
+
+    #[test]
+    fn initial_hold_applies_before_block_out() {
+        let mut game = game();
+        block_spawn(&mut game);
+        game.hold_piece = PieceKind::O;
+        game.swap_hold(PieceKind::I, None);
+        assert_eq!(game.top_out, None);
+        assert_eq!(game.current_piece.kind, PieceKind::O);
+        assert_eq!(game.hold_piece, PieceKind::I);
+        assert!(game.hold_used);
+    }
+
+    const LOCK_MS: f32 = 500.0;
+
+    // A T resting on an O in the bottom left corner, as if it had fallen
+    // there from the spawn row
+    fn on_ledge(policy: LockDelayPolicy) -> GameState {
+        let rules = RuleSet {
+            seed: Some(1),
+            lock_delay: policy,
+            lock_delay_ms: LOCK_MS,
+            ..RuleSet::default()
+        };
+        let mut game = GameState::with_rules(rules);
+        game.play_state = PlayState::Playing;
+        let bottom = game.board.height() as i32 - 1;
+        game.board.lock_piece(&Piece::new(PieceKind::O, bottom, 0));
+        game.current_piece = Piece::new(PieceKind::T, game.board.spawn_point().1, 1);
+        game.reset_lock_state();
+        game.current_piece.row = bottom - 2;
+        game
+    }
+    fn locked(game: &GameState) -> bool {
+        game.current_piece.kind == PieceKind::None
+    }
+
+    #[test]
+    fn move_reset_cap_runs_out() {
+        let mut game = on_ledge(LockDelayPolicy::MoveReset { max_resets: 15 });
+        game.player_interacting = true;
+        // touching down, then 14 moves that each restart the timer
+        for _ in 0..15 {
+            game.try_piece_lock(1.0);
+            assert!(!locked(&game));
+        }
+        assert_eq!(game.lock_resets, 14);
+        // the 15th uses up the resets, and a grounded piece locks at once
+        game.try_piece_lock(1.0);
+        assert!(locked(&game));
+    }
+
+    #[test]
+    fn move_reset_restarts_on_a_new_lowest_row() {
+        let mut game = on_ledge(LockDelayPolicy::MoveReset { max_resets: 15 });
+        game.player_interacting = true;
+        for _ in 0..11 {
+            game.try_piece_lock(1.0);
+        }
+        assert_eq!(game.lock_resets, 10);
+        // off the ledge and down to the floor
+        game.current_piece.col = 5;
+        game.current_piece.row += 2;
+        game.try_piece_lock(1.0);
+        assert_eq!(game.lock_resets, 0);
+        for _ in 0..14 {
+            game.try_piece_lock(1.0);
+            assert!(!locked(&game));
+        }
+        game.try_piece_lock(1.0);
+        assert!(locked(&game));
+    }
+
+    #[test]
+    fn move_reset_counts_nothing_before_touching_down() {
+        let mut game = on_ledge(LockDelayPolicy::MoveReset { max_resets: 15 });
+        game.player_interacting = true;
+        game.current_piece.row -= 3;
+        for _ in 0..20 {
+            game.try_piece_lock(1.0);
+        }
+        assert_eq!(game.lock_resets, 0);
+    }
+
+    #[test]
+    fn step_reset_only_resets_on_a_row_drop() {
+        let mut game = on_ledge(LockDelayPolicy::StepReset);
+        game.player_interacting = true;
+        game.try_piece_lock(LOCK_MS * 0.6);
+        // kicked up a row, then falling back is a step
+        game.current_piece.row -= 1;
+        game.try_piece_lock(LOCK_MS * 0.6);
+        game.current_piece.row += 1;
+        game.try_piece_lock(LOCK_MS * 0.6);
+        assert!(!locked(&game));
+        // moving on the ground doesn't reset it
+        game.try_piece_lock(LOCK_MS * 0.6);
+        assert!(locked(&game));
+    }
+
+    #[test]
+    fn no_reset_runs_down_through_steps() {
+        let mut game = on_ledge(LockDelayPolicy::NoReset);
+        game.player_interacting = true;
+        game.try_piece_lock(LOCK_MS * 0.6);
+        game.current_piece.row -= 1;
+        game.try_piece_lock(LOCK_MS * 0.6);
+        game.current_piece.row += 1;
+        game.try_piece_lock(LOCK_MS * 0.6);
+        assert!(locked(&game));
+    }
+
+    #[test]
+    fn infinite_never_locks() {
+        let mut game = on_ledge(LockDelayPolicy::Infinite);
+        for _ in 0..100 {
+            game.try_piece_lock(LOCK_MS);
+        }
+        assert!(!locked(&game));
+    }
+}
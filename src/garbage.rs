@@ -0,0 +1,64 @@
+use crate::rng::Rng;
+
+// Picks the hole column for each garbage row
+#[derive(Clone, Debug)]
+pub struct GarbageGenerator {
+    rng: Rng,
+    hole: Option<i32>,
+}
+impl GarbageGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Rng::new(seed), hole: None }
+    }
+    // `messiness` is the chance (0 to 1) that the hole moves to another
+    // column rather than lining up with the previous row's
+    pub fn next_hole(&mut self, width: usize, messiness: f32) -> i32 {
+        // no columns to put a hole in
+        if width == 0 {
+            return 0;
+        }
+        let hole = match self.hole {
+            Some(hole) if width < 2 || !self.rng.chance(messiness) => hole,
+            // any column but the current one
+            Some(hole) => (hole + 1 + self.rng.below(width - 1) as i32) % width as i32,
+            None => self.rng.below(width) as i32,
+        };
+        self.hole = Some(hole);
+        hole
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_garbage_keeps_its_hole() {
+        let mut garbage = GarbageGenerator::new(3);
+        let first = garbage.next_hole(10, 0.0);
+        assert!((0..10).contains(&first));
+        for _ in 0..20 {
+            assert_eq!(garbage.next_hole(10, 0.0), first);
+        }
+    }
+
+    #[test]
+    fn messy_garbage_always_moves_its_hole() {
+        let mut garbage = GarbageGenerator::new(3);
+        let mut last = garbage.next_hole(10, 1.0);
+        for _ in 0..50 {
+            let hole = garbage.next_hole(10, 1.0);
+            assert!((0..10).contains(&hole));
+            assert_ne!(hole, last);
+            last = hole;
+        }
+    }
+
+    #[test]
+    fn narrow_boards() {
+        let mut garbage = GarbageGenerator::new(3);
+        assert_eq!(garbage.next_hole(0, 1.0), 0);
+        assert_eq!(garbage.next_hole(1, 1.0), 0);
+        assert_eq!(garbage.next_hole(1, 1.0), 0);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::constants::*;
+
+// NES frames per row for levels 0..=28, level 29+ is 1 frame per row
+const NES_FRAMES_PER_ROW: [f32; 29] = [
+    48.0, 43.0, 38.0, 33.0, 28.0, 23.0, 18.0, 13.0, 8.0, 6.0,
+    5.0, 5.0, 5.0, 4.0, 4.0, 4.0, 3.0, 3.0, 3.0, 2.0,
+    2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0,
+];
+const NES_FRAME_MS: f32 = SECOND / 60.0988;
+// Past this level the guideline formula stops getting faster (and its base
+// would eventually go negative)
+const GUIDELINE_MAX_LEVEL: i32 = 20;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GravityCurve {
+    // (0.8 - (level-1) * 0.007)^(level-1) seconds per row
+    Guideline,
+    // NES speeds, game level 1 is NES level 0
+    Nes,
+    // Same speed at every level
+    Fixed(f32),
+}
+impl GravityCurve {
+    // Curves the start menu offers
+    pub const ALL: [GravityCurve; 3] = [GravityCurve::Guideline, GravityCurve::Nes, GravityCurve::Fixed(0.0)];
+    pub fn name(&self) -> String {
+        match self {
+            GravityCurve::Guideline => "Guideline".to_string(),
+            GravityCurve::Nes => "NES".to_string(),
+            GravityCurve::Fixed(ms) if *ms <= MAX_GRAVITY_MS_PER_ROW => "20G".to_string(),
+            GravityCurve::Fixed(ms) => format!("{:.0} ms/row", ms),
+        }
+    }
+    // Milliseconds per row at `level`. Can be well under a frame, in which
+    // case the piece falls several rows per frame, but never faster than 20G.
+    pub fn ms_per_row(&self, level: i32) -> f32 {
+        let ms = match self {
+            GravityCurve::Guideline => {
+                let n = (level.clamp(1, GUIDELINE_MAX_LEVEL) - 1) as f32;
+                (0.8 - n * 0.007).powf(n) * SECOND
+            },
+            GravityCurve::Nes => {
+                let idx = (level - 1).max(0) as usize;
+                let frames = NES_FRAMES_PER_ROW.get(idx).copied().unwrap_or(1.0);
+                frames * NES_FRAME_MS
+            },
+            GravityCurve::Fixed(ms) => *ms,
+        };
+        ms.max(MAX_GRAVITY_MS_PER_ROW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn guideline_curve() {
+        let curve = GravityCurve::Guideline;
+        assert!(close(curve.ms_per_row(1), 1000.0));
+        assert!(close(curve.ms_per_row(2), 793.0));
+        assert!(close(curve.ms_per_row(3), 0.786 * 0.786 * 1000.0));
+        // below level 1 and past the cap the curve holds its end values
+        assert_eq!(curve.ms_per_row(0), curve.ms_per_row(1));
+        assert_eq!(curve.ms_per_row(40), curve.ms_per_row(GUIDELINE_MAX_LEVEL));
+        assert!(curve.ms_per_row(GUIDELINE_MAX_LEVEL) >= MAX_GRAVITY_MS_PER_ROW);
+    }
+
+    #[test]
+    fn nes_curve() {
+        let curve = GravityCurve::Nes;
+        assert!(close(curve.ms_per_row(1), 48.0 * NES_FRAME_MS));
+        assert!(close(curve.ms_per_row(10), 6.0 * NES_FRAME_MS));
+        assert!(close(curve.ms_per_row(29), 2.0 * NES_FRAME_MS));
+        assert!(close(curve.ms_per_row(30), NES_FRAME_MS));
+        assert!(close(curve.ms_per_row(99), NES_FRAME_MS));
+    }
+
+    #[test]
+    fn never_faster_than_20g() {
+        assert_eq!(GravityCurve::Fixed(0.0).ms_per_row(1), MAX_GRAVITY_MS_PER_ROW);
+        assert_eq!(GravityCurve::Fixed(500.0).ms_per_row(15), 500.0);
+        assert_eq!(GravityCurve::Fixed(0.0).name(), "20G");
+    }
+}
@@ -4,7 +4,18 @@ mod constants;
 mod game; 
 mod render;
 mod piece_kind;
-mod button;
+mod piece_set;
+mod rng;
+mod randomizer;
+mod rules;
+mod settings;
+mod gravity;
+mod rotation;
+mod scoring;
+mod mode;
+mod records;
+mod garbage;
+mod storage;
 use macroquad::prelude::*;
 use crate::game::*;
 use macroquad::miniquad::date;
@@ -0,0 +1,184 @@
+use crate::constants::SECOND;
+
+// Line targets a sprint can be set to
+pub const SPRINT_LINE_OPTIONS: [i32; 3] = [20, 40, 100];
+pub const SPRINT_DEFAULT_LINES: i32 = 40;
+// Ultra time limits in seconds
+pub const ULTRA_SECONDS_OPTIONS: [i32; 3] = [60, 120, 180];
+pub const ULTRA_DEFAULT_SECONDS: i32 = 120;
+// Lines between sprint splits
+pub const SPRINT_SPLIT_LINES: i32 = 10;
+// Marathon lengths, the level cap is one level per 10 lines
+pub const MARATHON_LINE_OPTIONS: [i32; 2] = [150, 200];
+pub const MARATHON_MAX_START_LEVEL: i32 = 15;
+// Dig garbage heights, and chances in percent that a row's hole moves
+pub const DIG_ROW_OPTIONS: [i32; 3] = [5, 10, 18];
+pub const DIG_MESSINESS_OPTIONS: [i32; 4] = [0, 25, 50, 100];
+// Survival: seconds between rising rows at the start, and how that
+// shrinks - by SURVIVAL_SPEEDUP every SURVIVAL_SPEEDUP_MS, down to
+// SURVIVAL_MIN_INTERVAL_MS
+pub const SURVIVAL_SECONDS_OPTIONS: [i32; 3] = [4, 8, 12];
+const SURVIVAL_SPEEDUP: f32 = 0.9;
+const SURVIVAL_SPEEDUP_MS: f32 = 30.0 * SECOND;
+const SURVIVAL_MIN_INTERVAL_MS: f32 = SECOND;
+// Survival garbage is a little messy so it can't be cleared blind
+const SURVIVAL_MESSINESS: f32 = 0.3;
+
+// What the player is trying to do, and when the game is over
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum GameMode {
+    // No goal, plays until top out
+    #[default]
+    Endless,
+    // Clear `lines` lines as fast as possible
+    Sprint { lines: i32 },
+    // Score as much as possible in `seconds`
+    Ultra { seconds: i32 },
+    // Clear `lines` lines starting from `start_level`. With a variable
+    // goal each level needs 5 x level goal points instead of 10 lines.
+    Marathon { lines: i32, start_level: i32, variable_goal: bool },
+    // Dig through `rows` rows of garbage. `messiness` is the percent
+    // chance each row's hole is in a different column from the one below.
+    Dig { rows: i32, messiness: i32 },
+    // Last as long as possible while garbage rises from below, every
+    // `seconds` at first and more often as time goes on
+    Survival { seconds: i32 },
+}
+impl GameMode {
+    // Modes in the order the start menu cycles through them
+    pub fn all() -> Vec<GameMode> {
+        vec![
+            GameMode::Endless,
+            GameMode::Sprint { lines: SPRINT_DEFAULT_LINES },
+            GameMode::Ultra { seconds: ULTRA_DEFAULT_SECONDS },
+            GameMode::Marathon { lines: MARATHON_LINE_OPTIONS[0], start_level: 1, variable_goal: false },
+            GameMode::Dig { rows: DIG_ROW_OPTIONS[1], messiness: DIG_MESSINESS_OPTIONS[1] },
+            GameMode::Survival { seconds: SURVIVAL_SECONDS_OPTIONS[1] },
+        ]
+    }
+    pub fn name(&self) -> String {
+        match self {
+            GameMode::Endless => "ENDLESS".to_string(),
+            GameMode::Sprint { lines } => format!("SPRINT {}L", lines),
+            GameMode::Ultra { seconds } => format!("ULTRA {}:{:02}", seconds / 60, seconds % 60),
+            GameMode::Marathon { lines, .. } => format!("MARATHON {}L", lines),
+            GameMode::Dig { rows, .. } => format!("DIG {}", rows),
+            GameMode::Survival { .. } => "SURVIVAL".to_string(),
+        }
+    }
+    // Next or previous mode (dir 1 / -1), with its default options
+    pub fn cycle(&self, dir: i32) -> GameMode {
+        let modes = Self::all();
+        let idx = modes.iter()
+            .position(|m| std::mem::discriminant(m) == std::mem::discriminant(self))
+            .unwrap_or(0) as i32;
+        let len = modes.len() as i32;
+        modes[((idx + dir).rem_euclid(len)) as usize]
+    }
+    // The mode's options for the start menu, as (label, value)
+    pub fn options(&self) -> Vec<(&'static str, String)> {
+        match self {
+            GameMode::Endless => vec![],
+            GameMode::Sprint { lines } => vec![("Lines", lines.to_string())],
+            GameMode::Ultra { seconds } => vec![("Time", format!("{}:{:02}", seconds / 60, seconds % 60))],
+            GameMode::Marathon { lines, start_level, variable_goal } => vec![
+                ("Lines", lines.to_string()),
+                ("Start level", start_level.to_string()),
+                ("Goal", if *variable_goal { "Variable" } else { "Fixed" }.to_string()),
+            ],
+            GameMode::Dig { rows, messiness } => vec![
+                ("Rows", rows.to_string()),
+                ("Messiness", format!("{}%", messiness)),
+            ],
+            GameMode::Survival { seconds } => vec![("Rise every", format!("{}s", seconds))],
+        }
+    }
+    // Step option `idx` of options()
+    pub fn adjust(&self, idx: usize, dir: i32) -> GameMode {
+        match (*self, idx) {
+            (GameMode::Sprint { lines }, 0) => GameMode::Sprint { lines: step(&SPRINT_LINE_OPTIONS, lines, dir) },
+            (GameMode::Ultra { seconds }, 0) => GameMode::Ultra { seconds: step(&ULTRA_SECONDS_OPTIONS, seconds, dir) },
+            (GameMode::Marathon { lines, start_level, variable_goal }, _) => match idx {
+                0 => GameMode::Marathon { lines: step(&MARATHON_LINE_OPTIONS, lines, dir), start_level, variable_goal },
+                1 => GameMode::Marathon {
+                    lines,
+                    start_level: (start_level - 1 + dir).rem_euclid(MARATHON_MAX_START_LEVEL) + 1,
+                    variable_goal,
+                },
+                _ => GameMode::Marathon { lines, start_level, variable_goal: !variable_goal },
+            },
+            (GameMode::Dig { rows, messiness }, 0) => GameMode::Dig { rows: step(&DIG_ROW_OPTIONS, rows, dir), messiness },
+            (GameMode::Dig { rows, messiness }, _) => GameMode::Dig {
+                rows,
+                messiness: step(&DIG_MESSINESS_OPTIONS, messiness, dir),
+            },
+            (GameMode::Survival { seconds }, 0) => GameMode::Survival {
+                seconds: step(&SURVIVAL_SECONDS_OPTIONS, seconds, dir),
+            },
+            _ => *self,
+        }
+    }
+    // Lines that end the game, if any
+    pub fn line_goal(&self) -> Option<i32> {
+        match self {
+            GameMode::Sprint { lines } | GameMode::Marathon { lines, .. } => Some(*lines),
+            _ => None,
+        }
+    }
+    // Play time that ends the game, if any
+    pub fn time_limit_ms(&self) -> Option<f32> {
+        match self {
+            GameMode::Ultra { seconds } => Some(*seconds as f32 * SECOND),
+            _ => None,
+        }
+    }
+    pub fn start_level(&self) -> i32 {
+        match self {
+            GameMode::Marathon { start_level, .. } => *start_level,
+            _ => 1,
+        }
+    }
+    // Highest level the game reaches, if capped
+    pub fn max_level(&self) -> Option<i32> {
+        match self {
+            GameMode::Marathon { lines, .. } => Some(lines / 10),
+            _ => None,
+        }
+    }
+    // Garbage rows the board starts with, the game ends once they're gone
+    pub fn garbage_rows(&self) -> i32 {
+        match self {
+            GameMode::Dig { rows, .. } => *rows,
+            _ => 0,
+        }
+    }
+    // Chance (0 to 1) a garbage row's hole moves, see GarbageGenerator
+    pub fn messiness(&self) -> f32 {
+        match self {
+            GameMode::Dig { messiness, .. } => *messiness as f32 / 100.0,
+            GameMode::Survival { .. } => SURVIVAL_MESSINESS,
+            _ => 0.0,
+        }
+    }
+    // Time until the next garbage row rises, `elapsed_ms` into the game.
+    // None if garbage never rises.
+    pub fn rise_interval_ms(&self, elapsed_ms: f32) -> Option<f32> {
+        match self {
+            GameMode::Survival { seconds } => {
+                let speedup = SURVIVAL_SPEEDUP.powf(elapsed_ms / SURVIVAL_SPEEDUP_MS);
+                Some((*seconds as f32 * SECOND * speedup).max(SURVIVAL_MIN_INTERVAL_MS))
+            },
+            _ => None,
+        }
+    }
+    pub fn variable_goal(&self) -> bool {
+        matches!(self, GameMode::Marathon { variable_goal: true, .. })
+    }
+}
+
+// The option after (or before) `current` in `options`, wrapping around
+pub fn step<T: Copy + PartialEq>(options: &[T], current: T, dir: i32) -> T {
+    let idx = options.iter().position(|&o| o == current).unwrap_or(0) as i32;
+    let len = options.len() as i32;
+    options[((idx + dir).rem_euclid(len)) as usize]
+}
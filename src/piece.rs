@@ -1,29 +1,28 @@
 use macroquad::prelude::*;
-use crate::constants::*;
 use crate::board::Board;
 use crate::piece_kind::*;
-use macroquad::rand::gen_range;
-const KICKS_OFFSETS: [(i32, i32); 5] = [
-    (0,0),
-    (1, 0),
-    (-1, 0),
-    (0, 1),
-    (0, -1),
-];
+use crate::rotation::RotationSystem;
+use crate::piece_set::PieceSet;
 
 struct Pose {
     row: i32,
     col:i32,
     orientation: u8
 }
-pub enum RotDir { CW,CCW }
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RotDir { CW,CCW,Half }
 
-#[derive(Copy, Clone, Debug)] 
+#[derive(Clone, Debug)] 
 pub struct Piece {
     pub kind: PieceKind,
     pub row:i32,
     pub col:i32,
-    pub orientation: u8 //0,1,2,3
+    pub orientation: u8, //0,1,2,3
+    // decides the piece's cells and kicks
+    pub system: RotationSystem,
+    // the set it was dealt from, for custom kinds' cells
+    pub pieces: PieceSet,
 }
 impl Piece {
     pub fn default() -> Self {
@@ -31,30 +30,25 @@ impl Piece {
             kind: PieceKind::None,
             row: 0,
             col: 0,
-            orientation: 0
+            orientation: 0,
+            system: RotationSystem::Srs,
+            pieces: PieceSet::default(),
         }
     }
     pub fn new(kind: PieceKind, row:i32, col:i32) -> Self {
-        Piece { kind, col, row, orientation:0 }
+        Piece { kind, col, row, orientation:0, system: RotationSystem::Srs, pieces: PieceSet::default() }
     }
-    pub fn random_piece() -> Piece {
-        let n = gen_range(0, 7);
-        let mut kind = PieceKind::None;
-        match n {
-            1 => kind = PieceKind::T,
-            2 => kind = PieceKind::O,
-            3 => kind = PieceKind::I,
-            4 => kind = PieceKind::Z,
-            5 => kind = PieceKind::S,
-            6 => kind = PieceKind::J,
-            7 => kind = PieceKind::L,
-            _ => {}
-        }
-        Piece::new( kind, 0, DEFAULT_SPAWN_COL )
+    pub fn spawn(kind: PieceKind, board: &Board, system: RotationSystem, pieces: &PieceSet) -> Piece {
+        let (col, row) = board.spawn_point();
+        let (dcol, drow) = system.spawn_offset(pieces, kind);
+        Piece { system, pieces: pieces.clone(), ..Piece::new( kind, row + drow, col + dcol ) }
+    }
+    pub fn color(&self) -> Color {
+        self.pieces.color(self.kind)
     }
 
     pub fn cells(&self) -> Vec<(i32, i32)> {
-        self.kind.rotations()[self.orientation as usize].iter()
+        self.system.cells(&self.pieces, self.kind, self.orientation).iter()
             .map(move | (dc,dr) | (self.col + dc, self.row + dr)).collect()
         
     }
@@ -73,26 +67,49 @@ impl Piece {
         }
         true
     }
+    // Can't move left, right or up: the all-spin test. Must be checked
+    // before the piece is locked into the board.
+    pub fn is_immobile(&self, board: &Board) -> bool {
+        !self.can_move(-1, 0, board) && !self.can_move(1, 0, board) && !self.can_move(0, -1, board)
+    }
+    // Number of rows the piece can fall before it is grounded
+    pub fn drop_distance(&self, board: &Board) -> i32 {
+        let mut rows = 0;
+        while self.can_move(0, rows + 1, board) {
+            rows += 1;
+        }
+        rows
+    }
+    // Where the piece would come to rest if dropped straight down
+    pub fn landing(&self, board: &Board) -> Piece {
+        let mut landed = self.clone();
+        landed.row += self.drop_distance(board);
+        landed
+    }
     pub fn try_move_piece(&mut self, col:i32, row: i32, board: &Board) -> bool {
         if self.can_move(col, row, board) {
             self.col += col;
             self.row += row;
             return true;
         }
-        return false;
+        false
     }
-    pub fn try_rotate( &mut self, dir: RotDir, board: &Board) -> bool {
+    // Returns the index of the kick that succeeded, or None if the rotation
+    // was blocked. Index 0 is always the unkicked rotation.
+    pub fn try_rotate( &mut self, dir: RotDir, board: &Board) -> Option<usize> {
         let new_orient = match dir {
             RotDir::CW => (self.orientation + 1) & 3,
-            RotDir::CCW => (self.orientation + 3) & 3
+            RotDir::CCW => (self.orientation + 3) & 3,
+            RotDir::Half => (self.orientation + 2) & 3,
         };
         let base = Pose { col: self.col, row: self.row, orientation: new_orient };
+        let kicks = self.system.kicks(self, new_orient, board);
         info!( "rotate: [{}]", new_orient );
-        self.try_offsets( board, base )
+        self.try_offsets( board, base, kicks )
     }
 
     fn fits_at(&self, board: &Board, pose: &Pose) -> bool {
-        for &(dc, dr) in self.kind.rotations()[pose.orientation as usize].iter() {
+        for &(dc, dr) in self.system.cells(&self.pieces, self.kind, pose.orientation).iter() {
             let c = pose.col + dc;
             let r = pose.row + dr;
             if !board.in_bounds(c, r) { return false; }
@@ -105,8 +122,9 @@ impl Piece {
         &mut self,
         board: &Board,
         base: Pose,
-    ) -> bool {
-        for &(kcol, krow) in &KICKS_OFFSETS {
+        kicks: &[(i32, i32)],
+    ) -> Option<usize> {
+        for (i, &(kcol, krow)) in kicks.iter().enumerate() {
             let candidate = Pose {
                 col: base.col + kcol,
                 row: base.row + krow,
@@ -116,17 +134,9 @@ impl Piece {
                 self.col = candidate.col;
                 self.row = candidate.row;
                 self.orientation = candidate.orientation;
-                return true;
+                return Some(i);
             }
         }
-        false
+        None
     }
-
-    pub fn try_kick(
-        &mut self,
-        board: &Board,
-    ) -> bool {
-        let base = Pose { col: self.col, row: self.row, orientation: self.orientation };
-        self.try_offsets( board, base )
-    } 
 }
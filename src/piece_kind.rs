@@ -1,5 +1,87 @@
 use macroquad::prelude::*;
 
+// SRS wall kick tables. Offsets are (col, row) with row growing downward,
+// i.e. the published tables with their y component negated.
+// Indexed by the orientation the piece is rotating *from*; [0] is the
+// clockwise transition, [1] the counter-clockwise one.
+const JLSTZ_KICKS: [[[(i32, i32); 5]; 2]; 4] = [
+    // 0 -> R, 0 -> L
+    [
+        [(0,0),(-1,0),(-1,-1),(0, 2),(-1, 2)],
+        [(0,0),( 1,0),( 1,-1),(0, 2),( 1, 2)],
+    ],
+    // R -> 2, R -> 0
+    [
+        [(0,0),( 1,0),( 1, 1),(0,-2),( 1,-2)],
+        [(0,0),( 1,0),( 1, 1),(0,-2),( 1,-2)],
+    ],
+    // 2 -> L, 2 -> R
+    [
+        [(0,0),( 1,0),( 1,-1),(0, 2),( 1, 2)],
+        [(0,0),(-1,0),(-1,-1),(0, 2),(-1, 2)],
+    ],
+    // L -> 0, L -> 2
+    [
+        [(0,0),(-1,0),(-1, 1),(0,-2),(-1,-2)],
+        [(0,0),(-1,0),(-1, 1),(0,-2),(-1,-2)],
+    ],
+];
+const I_KICKS: [[[(i32, i32); 5]; 2]; 4] = [
+    // 0 -> R, 0 -> L
+    [
+        [(0,0),(-2,0),( 1,0),(-2, 1),( 1,-2)],
+        [(0,0),(-1,0),( 2,0),(-1,-2),( 2, 1)],
+    ],
+    // R -> 2, R -> 0
+    [
+        [(0,0),(-1,0),( 2,0),(-1,-2),( 2, 1)],
+        [(0,0),( 2,0),(-1,0),( 2,-1),(-1, 2)],
+    ],
+    // 2 -> L, 2 -> R
+    [
+        [(0,0),( 2,0),(-1,0),( 2,-1),(-1, 2)],
+        [(0,0),( 1,0),(-2,0),( 1, 2),(-2,-1)],
+    ],
+    // L -> 0, L -> 2
+    [
+        [(0,0),( 1,0),(-2,0),( 1, 2),(-2,-1)],
+        [(0,0),(-2,0),( 1,0),(-2, 1),( 1,-2)],
+    ],
+];
+// SRS+ 180 kicks, shared by every piece but O. Indexed by the orientation
+// the piece is rotating from.
+const HALF_KICKS: [[(i32, i32); 6]; 4] = [
+    [(0,0),( 0,-1),( 1,-1),(-1,-1),( 1,0),(-1,0)], // 0 -> 2
+    [(0,0),( 1, 0),( 1,-2),( 1,-1),( 0,-2),( 0,-1)], // R -> L
+    [(0,0),( 0, 1),(-1, 1),( 1, 1),(-1,0),( 1,0)], // 2 -> 0
+    [(0,0),(-1, 0),(-1,-2),(-1,-1),( 0,-2),( 0,-1)], // L -> R
+];
+const NO_KICKS: [(i32, i32); 1] = [(0,0)];
+// Index of the last JLSTZ kick, the (1,2) one that promotes a T-spin mini
+pub const SRS_FAR_KICK: usize = 4;
+
+// Which wall kick table a piece uses
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum KickTable {
+    Jlstz,
+    I,
+    None,
+}
+impl KickTable {
+    // Kick offsets to try, in order, when rotating from `from` to `to`
+    pub fn kicks(&self, from: u8, to: u8) -> &'static [(i32,i32)] {
+        let turn = (to + 4 - from) & 3;
+        match (self, turn) {
+            (KickTable::None, _) => &NO_KICKS,
+            (_, 2) => &HALF_KICKS[from as usize],
+            (KickTable::I, 1) => &I_KICKS[from as usize][0],
+            (KickTable::I, _) => &I_KICKS[from as usize][1],
+            (_, 1) => &JLSTZ_KICKS[from as usize][0],
+            _ => &JLSTZ_KICKS[from as usize][1],
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum PieceKind {
     None
@@ -10,55 +92,80 @@ pub enum PieceKind {
     , S
     , J
     , L
-
+    // a piece from a loaded piece set, see piece_set.rs
+    , Custom(u16)
 }
 impl PieceKind {
-    pub const fn rotations(&self) -> &'static [[(i32,i32);4]; 4] {
-        // TODO: Validate orientation order - especially for T and L
+    // Every playable kind, in the order randomizers deal from
+    pub const ALL: [PieceKind; 7] = [
+        PieceKind::I,
+        PieceKind::O,
+        PieceKind::T,
+        PieceKind::S,
+        PieceKind::Z,
+        PieceKind::J,
+        PieceKind::L,
+    ];
+    // Cell offsets for an orientation: 0 = spawn, 1 = R, 2 = 180, 3 = L.
+    // Offsets are (col, row) around the rotation centre. Custom kinds are
+    // looked up in their PieceSet instead.
+    pub fn cells(&self, orientation: u8) -> &'static [(i32,i32)] {
+        &self.rotations()[orientation as usize & 3]
+    }
+    pub fn kick_table(&self) -> KickTable {
+        match self {
+            PieceKind::I => KickTable::I,
+            PieceKind::O | PieceKind::None => KickTable::None,
+            _ => KickTable::Jlstz,
+        }
+    }
+    // SRS orientations of the standard pieces. JLSTZ turn about (0,0),
+    // I and O are laid out in a 4x4 box whose (1,1) cell sits on (0,0).
+    const fn rotations(&self) -> &'static [[(i32,i32);4]; 4] {
         match self {
             PieceKind::O => &[
-                [(0,0),(0,1),(1,0),(1,1)],
-                [(0,0),(0,1),(1,0),(1,1)],
-                [(0,0),(0,1),(1,0),(1,1)],
-                [(0,0),(0,1),(1,0),(1,1)],
+                [(0,-1),(1,-1),(0,0),(1,0)],
+                [(0,-1),(1,-1),(0,0),(1,0)],
+                [(0,-1),(1,-1),(0,0),(1,0)],
+                [(0,-1),(1,-1),(0,0),(1,0)],
             ],
             PieceKind::I => &[
                 [(-1,0),(0,0),(1,0),(2,0)],
-                [(0,-1),(0,0),(0,1),(0,2)],
-                [(-1,0),(0,0),(1,0),(2,0)],
+                [(1,-1),(1,0),(1,1),(1,2)],
+                [(-1,1),(0,1),(1,1),(2,1)],
                 [(0,-1),(0,0),(0,1),(0,2)],
             ],
             PieceKind::T => &[
-                [(0,0),(1,0),(1,-1),(2,0)],
-                [(0,0),(0,1),(0,-1),(1,0)],
-                [(0,0),(1,0),(1,1),(2,0)],
-                [(1,0),(1,1),(1,-1),(0,0)],
+                [(-1,0),(0,0),(1,0),(0,-1)],
+                [(0,-1),(0,0),(0,1),(1,0)],
+                [(-1,0),(0,0),(1,0),(0,1)],
+                [(0,-1),(0,0),(0,1),(-1,0)],
             ],
             PieceKind::L => &[
-                [(0,0),(0,1),(0,2),(1,2)],
-                [(0,1),(1,1),(2,1),(2,0)],
-                [(0,0),(1,0),(2,0),(0,1)],
-                [(0,0),(1,0),(1,1),(1,2)],
-                ],
-                PieceKind::J => &[
-                [(1,0),(1,1),(1,2),(0,2)],
-                [(0,0),(0,1),(1,1),(2,1)],
-                [(0,0),(1,0),(0,1),(0,2)],
-                [(0,0),(1,0),(2,0),(2,1)],
+                [(1,-1),(-1,0),(0,0),(1,0)],
+                [(1,1),(0,-1),(0,0),(0,1)],
+                [(-1,1),(1,0),(0,0),(-1,0)],
+                [(-1,-1),(0,1),(0,0),(0,-1)],
+            ],
+            PieceKind::J => &[
+                [(-1,-1),(-1,0),(0,0),(1,0)],
+                [(1,-1),(0,-1),(0,0),(0,1)],
+                [(1,1),(1,0),(0,0),(-1,0)],
+                [(-1,1),(0,1),(0,0),(0,-1)],
             ],
             PieceKind::Z => &[
-                [(0,0),(1,0),(1,1),(2,1)],
-                [(1,0),(1,1),(0,1),(0,2)],
-                [(0,0),(1,0),(1,1),(2,1)],
-                [(1,0),(1,1),(0,1),(0,2)],
+                [(-1,-1),(0,-1),(0,0),(1,0)],
+                [(1,-1),(1,0),(0,0),(0,1)],
+                [(1,1),(0,1),(0,0),(-1,0)],
+                [(-1,1),(-1,0),(0,0),(0,-1)],
             ],
-            PieceKind::S =>&[
-                [(0,1),(1,1),(1,0),(2,0)],
-                [(0,0),(0,1),(1,1),(1,2)],
-                [(0,1),(1,1),(1,0),(2,0)],
-                [(0,0),(0,1),(1,1),(1,2)],
+            PieceKind::S => &[
+                [(0,-1),(1,-1),(-1,0),(0,0)],
+                [(1,0),(1,1),(0,-1),(0,0)],
+                [(0,1),(-1,1),(1,0),(0,0)],
+                [(-1,0),(-1,-1),(0,1),(0,0)],
             ],
-            PieceKind::None =>&[
+            PieceKind::None | PieceKind::Custom(_) => &[
                 [(0,0);4],
                 [(0,0);4],
                 [(0,0);4],
@@ -66,9 +173,9 @@ impl PieceKind {
             ],
         }
     }
+
     
-    pub const fn color(&self) -> Color {
-        // same as before
+    pub fn color(&self) -> Color {
         match self {
             PieceKind::O    => YELLOW,
             PieceKind::I    => BLUE,
@@ -78,6 +185,75 @@ impl PieceKind {
             PieceKind::Z    => RED,
             PieceKind::S    => SKYBLUE,
             PieceKind::None => BLACK,
+            PieceKind::Custom(_) => WHITE,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negated(kicks: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        kicks.iter().map(|&(c, r)| (-c, -r)).collect()
+    }
+
+    #[test]
+    fn jlstz_kicks_match_published_table() {
+        // 0 -> R from the guideline, y negated
+        assert_eq!(KickTable::Jlstz.kicks(0, 1), &[(0,0),(-1,0),(-1,-1),(0,2),(-1,2)]);
+        // L -> 0
+        assert_eq!(KickTable::Jlstz.kicks(3, 0), &[(0,0),(-1,0),(-1,1),(0,-2),(-1,-2)]);
+    }
+
+    #[test]
+    fn i_kicks_match_published_table() {
+        // 0 -> R from the guideline, y negated
+        assert_eq!(KickTable::I.kicks(0, 1), &[(0,0),(-2,0),(1,0),(-2,1),(1,-2)]);
+        // R -> 2
+        assert_eq!(KickTable::I.kicks(1, 2), &[(0,0),(-1,0),(2,0),(-1,-2),(2,1)]);
+    }
+
+    #[test]
+    fn quarter_turn_kicks_undo_the_reverse_turn() {
+        for table in [KickTable::Jlstz, KickTable::I] {
+            for from in 0..4u8 {
+                for to in [(from + 1) & 3, (from + 3) & 3] {
+                    let kicks = table.kicks(from, to);
+                    assert_eq!(kicks.len(), 5);
+                    assert_eq!(kicks[0], (0, 0));
+                    assert_eq!(negated(kicks), table.kicks(to, from), "{:?} {} -> {}", table, from, to);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn far_kick_is_the_one_two_row_kick() {
+        // the kick a T uses into a TST slot is two rows down
+        assert_eq!(KickTable::Jlstz.kicks(0, 3)[SRS_FAR_KICK], (1, 2));
+        assert_eq!(KickTable::Jlstz.kicks(0, 1)[SRS_FAR_KICK], (-1, 2));
+    }
+
+    #[test]
+    fn half_turn_kicks_match_srs_plus() {
+        // 0 -> 2 from SRS+, y negated
+        assert_eq!(KickTable::Jlstz.kicks(0, 2), &[(0,0),(0,-1),(1,-1),(-1,-1),(1,0),(-1,0)]);
+        // I shares the table
+        assert_eq!(KickTable::I.kicks(0, 2), KickTable::Jlstz.kicks(0, 2));
+        // 2 -> 0 undoes 0 -> 2, L -> R mirrors R -> L
+        assert_eq!(negated(KickTable::Jlstz.kicks(0, 2)), KickTable::Jlstz.kicks(2, 0));
+        let mirrored: Vec<(i32, i32)> = KickTable::Jlstz.kicks(1, 3).iter().map(|&(c, r)| (-c, r)).collect();
+        assert_eq!(mirrored, KickTable::Jlstz.kicks(3, 1));
+    }
+
+    #[test]
+    fn o_piece_never_kicks() {
+        assert_eq!(PieceKind::O.kick_table(), KickTable::None);
+        for from in 0..4u8 {
+            for to in 0..4u8 {
+                assert_eq!(KickTable::None.kicks(from, to), &[(0, 0)]);
+            }
+        }
+    }
+}
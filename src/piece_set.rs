@@ -0,0 +1,265 @@
+use std::sync::Arc;
+use macroquad::prelude::*;
+use crate::piece_kind::*;
+use crate::storage;
+
+// Where the player's own piece set is read from, see storage.rs
+const CUSTOM_STORAGE_KEY: &str = "wasm_tetris_pieces";
+
+// Which pieces a game deals from. Pentominoes and triminoes are data files
+// built into the binary, Custom is a file in the same format read at
+// runtime. The format is documented in pieces/pentominoes.txt.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PieceSetKind {
+    Standard,
+    Pentominoes,
+    Triminoes,
+    Custom,
+}
+impl PieceSetKind {
+    pub const ALL: [PieceSetKind; 4] = [
+        PieceSetKind::Standard,
+        PieceSetKind::Pentominoes,
+        PieceSetKind::Triminoes,
+        PieceSetKind::Custom,
+    ];
+    // Only Custom can fail, if nothing is saved or it doesn't parse
+    pub fn build(&self) -> Result<PieceSet, String> {
+        match self {
+            PieceSetKind::Standard => Ok(PieceSet::standard()),
+            PieceSetKind::Pentominoes => {
+                Ok(PieceSet::parse(include_str!("../pieces/pentominoes.txt")).expect("built-in pentominoes"))
+            },
+            PieceSetKind::Triminoes => {
+                Ok(PieceSet::parse(include_str!("../pieces/triminoes.txt")).expect("built-in triminoes"))
+            },
+            PieceSetKind::Custom => {
+                let src = storage::load(CUSTOM_STORAGE_KEY)
+                    .ok_or_else(|| format!("no custom pieces saved as {}", CUSTOM_STORAGE_KEY))?;
+                PieceSet::parse(&src).map_err(|e| format!("custom pieces: {}", e))
+            },
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            PieceSetKind::Standard    => "Standard",
+            PieceSetKind::Pentominoes => "Pentominoes",
+            PieceSetKind::Triminoes   => "Triminoes",
+            PieceSetKind::Custom      => "Custom",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PieceDef {
+    pub color: Color,
+    // cells per orientation, any number of cells
+    pub rotations: [Vec<(i32, i32)>; 4],
+    pub spawn_offset: (i32, i32),
+    pub kicks: KickTable,
+}
+
+// The pieces a game deals from, and the definitions behind their
+// PieceKind::Custom ids. Cheap to clone, every piece dealt carries one to
+// look up its cells.
+#[derive(Clone, Debug)]
+pub struct PieceSet {
+    // never empty
+    kinds: Arc<[PieceKind]>,
+    // indexed by Custom id
+    defs: Arc<[PieceDef]>,
+}
+impl Default for PieceSet {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+impl PieceSet {
+    pub fn standard() -> Self {
+        Self { kinds: Arc::from(PieceKind::ALL), defs: Arc::from([]) }
+    }
+    pub fn kinds(&self) -> &[PieceKind] {
+        &self.kinds
+    }
+    // Cell offsets for an orientation, see PieceKind::cells
+    pub fn cells(&self, kind: PieceKind, orientation: u8) -> &[(i32, i32)] {
+        match kind {
+            PieceKind::Custom(id) => &self.def(id).rotations[orientation as usize & 3],
+            _ => kind.cells(orientation),
+        }
+    }
+    // Added to the board's spawn point
+    pub fn spawn_offset(&self, kind: PieceKind) -> (i32, i32) {
+        match kind {
+            PieceKind::Custom(id) => self.def(id).spawn_offset,
+            _ => (0, 0),
+        }
+    }
+    pub fn kick_table(&self, kind: PieceKind) -> KickTable {
+        match kind {
+            PieceKind::Custom(id) => self.def(id).kicks,
+            _ => kind.kick_table(),
+        }
+    }
+    pub fn color(&self, kind: PieceKind) -> Color {
+        match kind {
+            PieceKind::Custom(id) => self.def(id).color,
+            _ => kind.color(),
+        }
+    }
+    fn def(&self, id: u16) -> &PieceDef {
+        &self.defs[id as usize]
+    }
+    // Format is documented in pieces/pentominoes.txt
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let mut defs: Vec<ParsedDef> = Vec::new();
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |msg: &str| format!("line {}: {}", i + 1, msg);
+            let mut words = line.split_whitespace();
+            let key = words.next().unwrap_or("");
+            let args: Vec<&str> = words.collect();
+            if key == "piece" {
+                let name = args.first().ok_or_else(|| err("piece needs a name"))?;
+                defs.push(ParsedDef::new(name));
+                continue;
+            }
+            let def = defs.last_mut().ok_or_else(|| err("expected 'piece' first"))?;
+            match key {
+                "color" => {
+                    let rgb = parse_ints(&args).filter(|v| v.len() == 3)
+                        .ok_or_else(|| err("color needs r g b"))?;
+                    if rgb.iter().any(|v| !(0..=255).contains(v)) {
+                        return Err(err("color values are 0-255"));
+                    }
+                    def.color = Color::from_rgba(rgb[0] as u8, rgb[1] as u8, rgb[2] as u8, 255);
+                },
+                "spawn" => {
+                    let off = parse_ints(&args).filter(|v| v.len() == 2)
+                        .ok_or_else(|| err("spawn needs col row"))?;
+                    def.spawn_offset = (off[0], off[1]);
+                },
+                "kicks" => {
+                    def.kicks = match args.first().copied() {
+                        Some("jlstz") => KickTable::Jlstz,
+                        Some("i")     => KickTable::I,
+                        Some("none")  => KickTable::None,
+                        _ => return Err(err("kicks must be jlstz, i or none")),
+                    };
+                },
+                "cells" => {
+                    let mut cells = Vec::new();
+                    for pair in &args {
+                        let (c, r) = pair.split_once(',').ok_or_else(|| err("cells are col,row"))?;
+                        let c = c.parse::<i32>().map_err(|_| err("bad col"))?;
+                        let r = r.parse::<i32>().map_err(|_| err("bad row"))?;
+                        cells.push((c, r));
+                    }
+                    if cells.is_empty() {
+                        return Err(err("cells needs at least one cell"));
+                    }
+                    def.rotations.push(cells);
+                },
+                _ => return Err(err(&format!("unknown key '{}'", key))),
+            }
+        }
+        if defs.is_empty() {
+            return Err("no pieces defined".to_string());
+        }
+        let defs = defs.into_iter().map(ParsedDef::finish).collect::<Result<Vec<_>, _>>()?;
+        let kinds = (0..defs.len()).map(|id| PieceKind::Custom(id as u16)).collect();
+        Ok(Self { kinds, defs: Arc::from(defs) })
+    }
+}
+
+fn parse_ints(args: &[&str]) -> Option<Vec<i32>> {
+    args.iter().map(|a| a.parse::<i32>().ok()).collect()
+}
+
+struct ParsedDef {
+    name: String,
+    color: Color,
+    spawn_offset: (i32, i32),
+    kicks: KickTable,
+    rotations: Vec<Vec<(i32, i32)>>,
+}
+impl ParsedDef {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            color: WHITE,
+            spawn_offset: (0, 0),
+            kicks: KickTable::Jlstz,
+            rotations: Vec::new(),
+        }
+    }
+    fn finish(mut self) -> Result<PieceDef, String> {
+        match self.rotations.len() {
+            // only the spawn orientation given: turn it clockwise about (0,0)
+            1 => {
+                for i in 0..3 {
+                    let next = self.rotations[i].iter().map(|&(c, r)| (-r, c)).collect();
+                    self.rotations.push(next);
+                }
+            },
+            4 => {},
+            n => return Err(format!("piece {}: expected 1 or 4 cells lines, got {}", self.name, n)),
+        }
+        let rotations = self.rotations.try_into().expect("four orientations");
+        Ok(PieceDef {
+            color: self.color,
+            rotations,
+            spawn_offset: self.spawn_offset,
+            kicks: self.kicks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(src: &str) -> String {
+        PieceSet::parse(src).expect_err("should not parse")
+    }
+
+    #[test]
+    fn built_in_sets_parse() {
+        for kind in [PieceSetKind::Standard, PieceSetKind::Pentominoes, PieceSetKind::Triminoes] {
+            assert!(!kind.build().unwrap().kinds().is_empty(), "{}", kind.name());
+        }
+        assert_eq!(PieceSetKind::Pentominoes.build().unwrap().kinds().len(), 18);
+    }
+
+    #[test]
+    fn one_cells_line_is_turned_clockwise() {
+        let set = PieceSet::parse("piece V\ncolor 1 2 3\ncells 0,0 1,0 0,-1\nkicks none").unwrap();
+        let kind = PieceKind::Custom(0);
+        assert_eq!(set.kinds(), &[kind]);
+        assert_eq!(set.cells(kind, 1), &[(0,0),(0,1),(1,0)]);
+        assert_eq!(set.kick_table(kind), KickTable::None);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse_err(""), "no pieces defined");
+        assert_eq!(parse_err("# only a comment\n"), "no pieces defined");
+        assert_eq!(parse_err("cells 0,0"), "line 1: expected 'piece' first");
+        assert_eq!(parse_err("piece"), "line 1: piece needs a name");
+        assert_eq!(parse_err("piece A\ncolor 1 2"), "line 2: color needs r g b");
+        assert_eq!(parse_err("piece A\ncolor 300 0 0"), "line 2: color values are 0-255");
+        assert_eq!(parse_err("piece A\ncolor 0 -1 0"), "line 2: color values are 0-255");
+        assert_eq!(parse_err("piece A\nspawn x 0"), "line 2: spawn needs col row");
+        assert_eq!(parse_err("piece A\nkicks srs"), "line 2: kicks must be jlstz, i or none");
+        assert_eq!(parse_err("piece A\ncells 0"), "line 2: cells are col,row");
+        assert_eq!(parse_err("piece A\ncells x,0"), "line 2: bad col");
+        assert_eq!(parse_err("piece A\ncells 0,y"), "line 2: bad row");
+        assert_eq!(parse_err("piece A\ncells"), "line 2: cells needs at least one cell");
+        assert_eq!(parse_err("piece A\nsize 3"), "line 2: unknown key 'size'");
+        assert_eq!(parse_err("piece A\ncells 0,0\ncells 0,0"), "piece A: expected 1 or 4 cells lines, got 2");
+        assert_eq!(parse_err("piece A"), "piece A: expected 1 or 4 cells lines, got 0");
+    }
+}
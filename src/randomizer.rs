@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+use crate::piece_kind::*;
+use crate::rng::Rng;
+
+// TGM2 tries this many times to find a piece not in its history
+const TGM_ROLLS: usize = 6;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RandomizerKind {
+    SevenBag,
+    FourteenBag,
+    Tgm,
+    Uniform,
+}
+impl RandomizerKind {
+    pub const ALL: [RandomizerKind; 4] = [
+        RandomizerKind::SevenBag,
+        RandomizerKind::FourteenBag,
+        RandomizerKind::Tgm,
+        RandomizerKind::Uniform,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            RandomizerKind::SevenBag    => "7-bag",
+            RandomizerKind::FourteenBag => "14-bag",
+            RandomizerKind::Tgm         => "TGM",
+            RandomizerKind::Uniform     => "Random",
+        }
+    }
+    // `kinds` is the piece set to deal from. "Seven" bag deals one of each
+    // kind per bag whatever the size of the set.
+    pub fn build(&self, seed: u64, kinds: &[PieceKind]) -> Box<dyn Randomizer> {
+        let kinds = kinds.to_vec();
+        match self {
+            RandomizerKind::SevenBag    => Box::new(BagRandomizer::new(seed, kinds, 1)),
+            RandomizerKind::FourteenBag => Box::new(BagRandomizer::new(seed, kinds, 2)),
+            RandomizerKind::Tgm         => Box::new(HistoryRandomizer::new(seed, kinds, TGM_ROLLS)),
+            RandomizerKind::Uniform     => Box::new(UniformRandomizer::new(seed, kinds)),
+        }
+    }
+}
+
+pub trait Randomizer {
+    fn next(&mut self) -> PieceKind;
+}
+
+// ======================================
+// N-bag: every piece `copies` times per bag, shuffled
+// ======================================
+pub struct BagRandomizer {
+    rng: Rng,
+    kinds: Vec<PieceKind>,
+    copies: usize,
+    bag: Vec<PieceKind>,
+}
+impl BagRandomizer {
+    pub fn new(seed: u64, kinds: Vec<PieceKind>, copies: usize) -> Self {
+        Self { rng: Rng::new(seed), kinds, copies, bag: Vec::new() }
+    }
+    fn refill(&mut self) {
+        for _ in 0..self.copies {
+            self.bag.extend_from_slice(&self.kinds);
+        }
+        self.rng.shuffle(&mut self.bag);
+    }
+}
+impl Randomizer for BagRandomizer {
+    fn next(&mut self) -> PieceKind {
+        if self.bag.is_empty() {
+            self.refill();
+        }
+        // bag is shuffled, so popping from the back is as good as the front
+        self.bag.pop().unwrap()
+    }
+}
+
+// ======================================
+// TGM: 4 piece history, reroll when the roll is in the history
+// ======================================
+pub struct HistoryRandomizer {
+    rng: Rng,
+    kinds: Vec<PieceKind>,
+    rolls: usize,
+    history: VecDeque<PieceKind>,
+    first: bool,
+}
+impl HistoryRandomizer {
+    pub fn new(seed: u64, kinds: Vec<PieceKind>, rolls: usize) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            kinds,
+            rolls,
+            history: VecDeque::from([PieceKind::Z, PieceKind::S, PieceKind::S, PieceKind::Z]),
+            first: true,
+        }
+    }
+    fn roll(&mut self) -> PieceKind {
+        self.kinds[self.rng.below(self.kinds.len())]
+    }
+}
+impl Randomizer for HistoryRandomizer {
+    fn next(&mut self) -> PieceKind {
+        let mut kind = self.roll();
+        let awkward = |k: &PieceKind| matches!(k, PieceKind::S | PieceKind::Z | PieceKind::O);
+        if self.first {
+            // The first piece is never an S, Z or O (if the set has anything else)
+            self.first = false;
+            let has_other = self.kinds.iter().any(|k| !awkward(k));
+            while has_other && awkward(&kind) {
+                kind = self.roll();
+            }
+        } else {
+            for _ in 1..self.rolls {
+                if !self.history.contains(&kind) {
+                    break;
+                }
+                kind = self.roll();
+            }
+        }
+        self.history.pop_front();
+        self.history.push_back(kind);
+        kind
+    }
+}
+
+// ======================================
+// Uniform: every piece independently random
+// ======================================
+pub struct UniformRandomizer {
+    rng: Rng,
+    kinds: Vec<PieceKind>,
+}
+impl UniformRandomizer {
+    pub fn new(seed: u64, kinds: Vec<PieceKind>) -> Self {
+        Self { rng: Rng::new(seed), kinds }
+    }
+}
+impl Randomizer for UniformRandomizer {
+    fn next(&mut self) -> PieceKind {
+        self.kinds[self.rng.below(self.kinds.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deal(kind: RandomizerKind, seed: u64, count: usize) -> Vec<PieceKind> {
+        let mut randomizer = kind.build(seed, &PieceKind::ALL);
+        (0..count).map(|_| randomizer.next()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        for kind in RandomizerKind::ALL {
+            assert_eq!(deal(kind, 42, 100), deal(kind, 42, 100), "{}", kind.name());
+        }
+    }
+
+    #[test]
+    fn different_seed_different_sequence() {
+        assert_ne!(deal(RandomizerKind::SevenBag, 1, 100), deal(RandomizerKind::SevenBag, 2, 100));
+    }
+
+    #[test]
+    fn seven_bag_deals_each_kind_once_per_bag() {
+        let pieces = deal(RandomizerKind::SevenBag, 7, 7 * 20);
+        for bag in pieces.chunks(7) {
+            for kind in PieceKind::ALL {
+                assert_eq!(bag.iter().filter(|&&k| k == kind).count(), 1, "{:?}", bag);
+            }
+        }
+    }
+
+    #[test]
+    fn fourteen_bag_deals_each_kind_twice_per_bag() {
+        let pieces = deal(RandomizerKind::FourteenBag, 7, 14 * 10);
+        for bag in pieces.chunks(14) {
+            for kind in PieceKind::ALL {
+                assert_eq!(bag.iter().filter(|&&k| k == kind).count(), 2, "{:?}", bag);
+            }
+        }
+    }
+
+    #[test]
+    fn tgm_never_starts_with_s_z_or_o() {
+        for seed in 0..200 {
+            let first = deal(RandomizerKind::Tgm, seed, 1)[0];
+            assert!(!matches!(first, PieceKind::S | PieceKind::Z | PieceKind::O), "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::storage;
+
+const STORAGE_KEY: &str = "wasm_tetris_records";
+
+// Personal bests. Like Settings these belong to the player and survive
+// restarts, and they are saved so they outlast the program too.
+#[derive(Clone, Debug, Default)]
+pub struct Records {
+    sprints: Vec<SprintRecord>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SprintRecord {
+    pub lines: i32,
+    pub time_ms: f32,
+    // elapsed time at every SPRINT_SPLIT_LINES lines
+    pub splits: Vec<f32>,
+}
+
+impl Records {
+    // The saved records, or none if nothing was saved
+    pub fn load() -> Self {
+        storage::load(STORAGE_KEY).map(|src| Self::parse(&src)).unwrap_or_default()
+    }
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, &self.serialize());
+    }
+    pub fn sprint_best(&self, lines: i32) -> Option<&SprintRecord> {
+        self.sprints.iter().find(|r| r.lines == lines)
+    }
+    // Keeps the run if it beats the best for its line count. Returns
+    // whether it did.
+    pub fn submit_sprint(&mut self, run: SprintRecord) -> bool {
+        match self.sprints.iter_mut().find(|r| r.lines == run.lines) {
+            Some(best) if best.time_ms <= run.time_ms => false,
+            Some(best) => {
+                *best = run;
+                true
+            },
+            None => {
+                self.sprints.push(run);
+                true
+            },
+        }
+    }
+    // One line per record: `sprint <lines> <time_ms> <split_ms>...`.
+    // Lines that don't parse are dropped rather than losing every record.
+    fn parse(src: &str) -> Self {
+        let mut records = Self::default();
+        for line in src.lines() {
+            let mut words = line.split_whitespace();
+            if words.next() != Some("sprint") {
+                continue;
+            }
+            let lines = words.next().and_then(|w| w.parse::<i32>().ok());
+            let time_ms = words.next().and_then(|w| w.parse::<f32>().ok());
+            let splits: Option<Vec<f32>> = words.map(|w| w.parse::<f32>().ok()).collect();
+            if let (Some(lines), Some(time_ms), Some(splits)) = (lines, time_ms, splits) {
+                records.submit_sprint(SprintRecord { lines, time_ms, splits });
+            }
+        }
+        records
+    }
+    fn serialize(&self) -> String {
+        let mut out = String::new();
+        for run in &self.sprints {
+            out += &format!("sprint {} {}", run.lines, run.time_ms);
+            for split in &run.splits {
+                out += &format!(" {}", split);
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(lines: i32, time_ms: f32) -> SprintRecord {
+        SprintRecord { lines, time_ms, splits: vec![time_ms / 2.0] }
+    }
+
+    #[test]
+    fn submit_sprint_keeps_the_fastest_per_line_count() {
+        let mut records = Records::default();
+        assert!(records.sprint_best(40).is_none());
+        assert!(records.submit_sprint(run(40, 60_000.0)));
+        assert!(!records.submit_sprint(run(40, 65_000.0)));
+        // a tie isn't a new record
+        assert!(!records.submit_sprint(run(40, 60_000.0)));
+        assert!(records.submit_sprint(run(40, 55_000.0)));
+        assert_eq!(records.sprint_best(40).unwrap().time_ms, 55_000.0);
+        assert!(records.submit_sprint(run(20, 70_000.0)));
+        assert_eq!(records.sprint_best(20).unwrap().time_ms, 70_000.0);
+        assert_eq!(records.sprint_best(40).unwrap().time_ms, 55_000.0);
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        let mut records = Records::default();
+        records.submit_sprint(SprintRecord { lines: 40, time_ms: 61234.5, splits: vec![15000.25, 30000.0] });
+        records.submit_sprint(run(20, 30_000.0));
+        let loaded = Records::parse(&records.serialize());
+        let best = loaded.sprint_best(40).unwrap();
+        assert_eq!(best.time_ms, 61234.5);
+        assert_eq!(best.splits, vec![15000.25, 30000.0]);
+        assert!(loaded.sprint_best(20).is_some());
+    }
+
+    #[test]
+    fn parse_drops_bad_lines() {
+        let records = Records::parse("sprint 40 x\nultra 120 5000\nsprint 20 30000 1x\n\nsprint 40 50000\n");
+        assert!(records.sprint_best(20).is_none());
+        assert_eq!(records.sprint_best(40).unwrap().time_ms, 50000.0);
+        assert!(records.sprint_best(40).unwrap().splits.is_empty());
+    }
+}
@@ -3,27 +3,65 @@ use crate::game::*;
 use crate::piece::Piece;
 use macroquad::prelude::*;
 use crate::board::Board;
+use crate::piece_kind::PieceKind;
+use crate::mode::*;
+
+// Where the board sits on screen this frame
+pub struct Layout {
+    pub x: f32,
+    pub cell: f32,
+    pub width: f32,
+    pub height: f32,
+    // board rows above the top of the screen (vanish zone)
+    pub hidden: i32,
+}
+impl Layout {
+    pub fn new(board: &Board) -> Self {
+        // shrink cells so tall boards still fit the window
+        let cell = SQUARE_SIZE.min(screen_height() / board.visible_height() as f32);
+        let width = board.width() as f32 * cell;
+        Self {
+            x: (screen_width() - width) / 2.0,
+            cell,
+            width,
+            height: board.visible_height() as f32 * cell,
+            hidden: board.hidden_rows(),
+        }
+    }
+}
 
 pub fn draw_frame(game: &GameState) {
+    let layout = Layout::new(&game.board);
     clear_background(BLACK);
-    render_background();
-    draw_buttons(&game);
+    render_background(&layout);
     match game.play_state {
             PlayState::Start => {
-                draw_text_centered(
-                    "Press ENTER to begin",
-                    300.0,
-                    50.0,
-                    WHITE,
-                );
+                draw_start_menu(game);
+            },
+            PlayState::Settings => {
+                draw_settings_menu(game);
             },
             PlayState::Playing => {
-                draw_board(&game.board);
-                draw_piece(&game.current_piece);
+                draw_board(&layout, &game.board);
+                if game.settings.show_ghost {
+                    draw_ghost(&layout, &game.current_piece, &game.board);
+                }
+                draw_piece(&layout, &game.current_piece);
+                draw_hold(&layout, game);
+                draw_next_queue(&layout, game);
+                draw_combo(&layout, game.combo);
+                draw_mode_hud(&layout, game);
                 draw_score(game.score);
             },
             PlayState::ClearBlocks => {
-                draw_board(&game.board);
+                draw_board(&layout, &game.board);
+                if game.last_clear.perfect_clear {
+                    draw_text_centered("PERFECT CLEAR", 300.0, 40.0, GOLD);
+                }
+                draw_hold(&layout, game);
+                draw_next_queue(&layout, game);
+                draw_combo(&layout, game.combo);
+                draw_mode_hud(&layout, game);
                 draw_score(game.score);
             },
             PlayState::Paused => {
@@ -40,6 +78,15 @@ pub fn draw_frame(game: &GameState) {
                     WHITE,
                 );
             },
+            PlayState::Complete => {
+                draw_text_centered(
+                    "Complete!",
+                    200.0,
+                    50.0,
+                    WHITE,
+                );
+                draw_results(game);
+            },
             PlayState::GameOver => {
                 draw_text_centered(
                     "Game Over!",
@@ -48,82 +95,285 @@ pub fn draw_frame(game: &GameState) {
                     WHITE,
                 );
                 draw_text_centered(
-                    &format!("Score: {0}", game.score.to_string()),
+                    &format!("Score: {0}", game.score),
                     360.0,
                     20.0,
                     WHITE,
                 );
+                if let Some(reason) = game.top_out {
+                    draw_text_centered(
+                        reason.describe(),
+                        390.0,
+                        20.0,
+                        GRAY,
+                    );
+                }
+                if let GameMode::Survival { .. } = game.mode {
+                    draw_text_centered(
+                        &format!("Survived {}", format_time(game.elapsed_ms)),
+                        420.0,
+                        20.0,
+                        WHITE,
+                    );
+                }
             }
         }
 }
-fn draw_piece(piece:&Piece) {
+fn draw_piece(layout: &Layout, piece:&Piece) {
     for (dcol, drow) in piece.cells() {
         let col:i32 = dcol;
         let row:i32 = drow;
-        draw_block(col, row,piece.kind.color(), DARKGRAY);
+        draw_block(layout, col, row,piece.color(), DARKGRAY);
     }
 }
-fn draw_board(board:&Board) {
+fn draw_ghost(layout: &Layout, piece:&Piece, board:&Board) {
+    let color = piece.color();
+    let fill = Color::new(color.r, color.g, color.b, 0.2);
+    for (col, row) in piece.landing(board).cells() {
+        draw_block(layout, col, row, fill, color);
+    }
+}
+fn draw_board(layout: &Layout, board:&Board) {
     for cell in board.filled_cells() {
-        draw_block(cell.col, cell.row, cell.color, DARKGRAY);
+        draw_block(layout, cell.col, cell.row, cell.color, DARKGRAY);
     }
 }
-fn render_background() {
+fn draw_hold(layout: &Layout, game: &GameState) {
+    if !game.rules.hold_enabled {
+        return;
+    }
+    let x = layout.x - PANEL_W - PANEL_MARGIN;
+    draw_text("HOLD", x, 20.0, 24.0, WHITE);
+    if game.hold_piece != PieceKind::None {
+        // greyed out until the next piece locks
+        let fill = if game.hold_used { GRAY } else { game.pieces.color(game.hold_piece) };
+        draw_preview_piece(game, game.hold_piece, x, 30.0, fill);
+    }
+}
+fn draw_next_queue(layout: &Layout, game: &GameState) {
+    let x = layout.x + layout.width + PANEL_MARGIN;
+    draw_text("NEXT", x, 20.0, 24.0, WHITE);
+    // stack pieces by their own height, one cell gap between them
+    let size = SQUARE_SIZE * PREVIEW_SCALE;
+    let mut y = 30.0;
+    for kind in game.next_queue().iter() {
+        let rows = draw_preview_piece(game, *kind, x, y, game.pieces.color(*kind));
+        y += (rows + 1) as f32 * size;
+    }
+}
+// Draws a piece in its spawn orientation at reduced scale, with its
+// bounding box starting at (x, y) in pixels. Returns its height in cells.
+fn draw_preview_piece(game: &GameState, kind: PieceKind, x: f32, y: f32, fill: Color) -> i32 {
+    let size = SQUARE_SIZE * PREVIEW_SCALE;
+    let cells = game.rules.rotation.cells(&game.pieces, kind, 0);
+    let min_col = cells.iter().map(|&(c, _)| c).min().unwrap_or(0);
+    let min_row = cells.iter().map(|&(_, r)| r).min().unwrap_or(0);
+    let max_row = cells.iter().map(|&(_, r)| r).max().unwrap_or(0);
+    for &(col, row) in cells.iter() {
+        let px = x + (col - min_col) as f32 * size;
+        let py = y + (row - min_row) as f32 * size;
+        draw_rectangle(px, py, size, size, fill);
+        draw_rectangle_lines(px, py, size, size, LINE_THICKNESS, DARKGRAY);
+    }
+    max_row - min_row + 1
+}
+fn render_background(layout: &Layout) {
     // draw boundaries 
-    let offset_x = (screen_width() - BOARD_W) / 2.0;
+    let offset_x = layout.x;
     // left border
     draw_line(
         offset_x,
         0.0,
         offset_x,
-        BOARD_H as f32,
+        layout.height,
         2.0,
         GRAY,
     );
     // right border
     draw_line(
-        offset_x + BOARD_W as f32,
+        offset_x + layout.width,
         0.0,
-        offset_x + BOARD_W as f32,
-        BOARD_H as f32,
+        offset_x + layout.width,
+        layout.height,
         2.0,
         GRAY,
     );
     // bottom border
     draw_line(
         offset_x,
-        BOARD_H as f32,
-        offset_x + BOARD_W as f32,
-        BOARD_H as f32,
+        layout.height,
+        offset_x + layout.width,
+        layout.height,
         2.0,
         GRAY,
     );
 }
 fn draw_score(score: i32) {
     let text = score.to_string();
-    let dims = measure_text(&text, None, 24 as u16, 1.0);
+    let dims = measure_text(&text, None, 24_u16, 1.0);
     let x = screen_width() - dims.width - 15.0;
     draw_text(&text, x, 50.0, 24.0, WHITE );
 }
-pub fn coords_to_pixels(col: i32, row: i32) -> (f32, f32) {
-    (col as f32 * SQUARE_SIZE, row as f32 * SQUARE_SIZE)
+// Mode, its options and the rules, the selected row highlighted
+fn draw_start_menu(game: &GameState) {
+    let color = |row: usize| if game.menu_row == row { YELLOW } else { WHITE };
+    draw_text_centered(&format!("< {} >", game.mode.name()), 100.0, 40.0, color(0));
+    let mut y = 140.0;
+    let options = game.mode.options().into_iter().chain(game.rules.options());
+    for (i, (label, value)) in options.enumerate() {
+        draw_text_centered(&format!("{}: < {} >", label, value), y, 24.0, color(i + 1));
+        y += 28.0;
+    }
+    let best = match game.mode {
+        GameMode::Sprint { lines } => game.records.sprint_best(lines),
+        _ => None,
+    };
+    if let Some(best) = best {
+        draw_text_centered(&format!("Best: {}", format_time(best.time_ms)), y, 20.0, GRAY);
+        y += 24.0;
+    }
+    if let Some(error) = &game.pieces_error {
+        draw_text_centered(&format!("{}, using standard pieces", error), y, 20.0, RED);
+        y += 24.0;
+    }
+    draw_text_centered("UP/DOWN: select   LEFT/RIGHT: change   TAB: settings", y + 10.0, 20.0, GRAY);
+    draw_text_centered("Press ENTER to begin", y + 70.0, 50.0, WHITE);
+}
+fn draw_settings_menu(game: &GameState) {
+    draw_text_centered("SETTINGS", 100.0, 40.0, WHITE);
+    let mut y = 140.0;
+    for (i, (label, value)) in game.settings.options().iter().enumerate() {
+        let color = if game.settings_row == i { YELLOW } else { WHITE };
+        draw_text_centered(&format!("{}: < {} >", label, value), y, 24.0, color);
+        y += 28.0;
+    }
+    draw_text_centered("UP/DOWN: select   LEFT/RIGHT: change   ESC: back", y + 10.0, 20.0, GRAY);
 }
-pub fn draw_block(col:i32, row:i32, fill: Color, border:Color) {
-    let (px, py) = coords_to_pixels(col, row);
-    let offset_x = px + (screen_width() - BOARD_W) / 2.0;
-    //let offset_y = py + (screen_height() - BOARD_H) / 2.0;
+// Mode progress under the hold panel
+fn draw_mode_hud(layout: &Layout, game: &GameState) {
+    let x = layout.x - PANEL_W - PANEL_MARGIN;
+    match game.mode {
+        GameMode::Sprint { lines } => {
+            draw_text(&format_time(game.elapsed_ms), x, 180.0, 24.0, WHITE);
+            draw_text(&format!("{}/{}", game.lines_cleared(), lines), x, 205.0, 24.0, WHITE);
+            let last = game.splits.len().checked_sub(1);
+            if let Some((delta, color)) = last.and_then(|i| split_delta(game, i)) {
+                draw_text(&delta, x, 230.0, 24.0, color);
+            }
+        },
+        GameMode::Ultra { .. } => {
+            let limit = game.mode.time_limit_ms().unwrap_or(0.0);
+            let left = limit - game.elapsed_ms;
+            // last ten seconds in red
+            let color = if left < 10.0 * SECOND { RED } else { WHITE };
+            draw_text(&format_time(left), x, 180.0, 24.0, color);
+        },
+        GameMode::Marathon { lines, .. } => {
+            draw_text(&format!("LEVEL {}", game.level()), x, 180.0, 24.0, WHITE);
+            draw_text(&format!("{}/{}", game.lines_cleared(), lines), x, 205.0, 24.0, WHITE);
+            if let Some(goal) = game.goal_remaining() {
+                draw_text(&format!("GOAL {}", goal), x, 230.0, 24.0, WHITE);
+            }
+        },
+        GameMode::Dig { .. } => {
+            draw_text(&format_time(game.elapsed_ms), x, 180.0, 24.0, WHITE);
+            draw_text(&format!("{} LEFT", game.board.garbage_rows_left()), x, 205.0, 24.0, WHITE);
+        },
+        GameMode::Survival { .. } => {
+            draw_text(&format_time(game.elapsed_ms), x, 180.0, 24.0, WHITE);
+        },
+        GameMode::Endless => {},
+    }
+}
+// A split against the personal best's split at the same line count
+fn split_delta(game: &GameState, idx: usize) -> Option<(String, Color)> {
+    let best = game.best_sprint.as_ref()?.splits.get(idx)?;
+    let delta = game.splits[idx] - best;
+    let sign = if delta < 0.0 { "-" } else { "+" };
+    let color = if delta < 0.0 { GREEN } else { RED };
+    Some((format!("{}{}", sign, format_time(delta.abs())), color))
+}
+fn draw_results(game: &GameState) {
+    match game.mode {
+        GameMode::Sprint { .. } => draw_sprint_results(game),
+        GameMode::Ultra { .. } => draw_ultra_results(game),
+        GameMode::Marathon { .. } => draw_marathon_results(game),
+        GameMode::Dig { .. } => draw_dig_results(game),
+        GameMode::Survival { .. } | GameMode::Endless => {},
+    }
+}
+fn draw_sprint_results(game: &GameState) {
+    draw_text_centered(&format_time(game.elapsed_ms), 250.0, 40.0, WHITE);
+    let mut y = 290.0;
+    for (i, split) in game.splits.iter().enumerate() {
+        let lines = (i as i32 + 1) * SPRINT_SPLIT_LINES;
+        let mut text = format!("{:>3}  {}", lines, format_time(*split));
+        if let Some((delta, _)) = split_delta(game, i) {
+            text = format!("{}  {}", text, delta);
+        }
+        draw_text_centered(&text, y, 20.0, GRAY);
+        y += 22.0;
+    }
+    if game.new_record {
+        draw_text_centered("New personal best!", y + 10.0, 24.0, GOLD);
+    }
+}
+fn draw_ultra_results(game: &GameState) {
+    draw_text_centered(&format!("Score: {}", game.score), 250.0, 40.0, WHITE);
+    let ppp = game.stats.points_per_piece(game.score);
+    let text = format!("{} pieces, {:.1} points per piece", game.stats.pieces, ppp);
+    draw_text_centered(&text, 280.0, 20.0, GRAY);
+    let mut y = 310.0;
+    for (result, count) in game.stats.clears.iter() {
+        draw_text_centered(&format!("{}: {}", result.name(), count), y, 20.0, GRAY);
+        y += 22.0;
+    }
+}
+fn draw_marathon_results(game: &GameState) {
+    draw_text_centered(&format!("Score: {}", game.score), 250.0, 40.0, WHITE);
+    let text = format!("Level {}   {} lines", game.level(), game.lines_cleared());
+    draw_text_centered(&text, 280.0, 20.0, GRAY);
+    draw_text_centered(&format_time(game.elapsed_ms), 305.0, 20.0, GRAY);
+}
+fn draw_dig_results(game: &GameState) {
+    draw_text_centered(&format_time(game.elapsed_ms), 250.0, 40.0, WHITE);
+    draw_text_centered(&format!("{} pieces", game.stats.pieces), 280.0, 20.0, GRAY);
+}
+// m:ss.mmm
+fn format_time(ms: f32) -> String {
+    let total = ms.max(0.0) as u32;
+    format!("{}:{:02}.{:03}", total / 60_000, total / 1000 % 60, total % 1000)
+}
+fn draw_combo(layout: &Layout, combo: i32) {
+    if combo < 1 {
+        return;
+    }
+    let x = layout.x - PANEL_W - PANEL_MARGIN;
+    draw_text(&format!("COMBO {}", combo), x, 140.0, 24.0, WHITE);
+}
+// The first visible board row is the top of the screen
+pub fn coords_to_pixels(layout: &Layout, col: i32, row: i32) -> (f32, f32) {
+    (col as f32 * layout.cell, (row - layout.hidden) as f32 * layout.cell)
+}
+pub fn draw_block(layout: &Layout, col:i32, row:i32, fill: Color, border:Color) {
+    // vanish zone is not drawn
+    if row < layout.hidden {
+        return;
+    }
+    let (px, py) = coords_to_pixels(layout, col, row);
+    let offset_x = px + layout.x;
     let offset_y: f32 = py;
     draw_rectangle
         ( offset_x
         , offset_y
-        , SQUARE_SIZE
-        , SQUARE_SIZE
+        , layout.cell
+        , layout.cell
         , fill);
     draw_rectangle_lines
         ( offset_x
         , offset_y
-        , SQUARE_SIZE
-        , SQUARE_SIZE
+        , layout.cell
+        , layout.cell
         , LINE_THICKNESS 
         , border);
 }
@@ -132,6 +382,3 @@ pub fn draw_text_centered(text: &str, y: f32, size: f32, color: Color) {
     let x = (screen_width() - dims.width) * 0.5;
     draw_text(text, x, y, size, color);
 }
-fn draw_buttons(game:&GameState ) {
-
-}
\ No newline at end of file
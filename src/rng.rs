@@ -0,0 +1,33 @@
+// Small seedable PRNG (splitmix64). Game logic draws from this instead of
+// macroquad's global generator so that a seed fully reproduces a game.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    // Uniform integer in [0, max)
+    pub fn below(&mut self, max: usize) -> usize {
+        (self.next_u64() % max as u64) as usize
+    }
+    // True with probability `p`
+    pub fn chance(&mut self, p: f32) -> bool {
+        // top 24 bits, exactly representable in an f32
+        ((self.next_u64() >> 40) as f32 / (1u64 << 24) as f32) < p
+    }
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
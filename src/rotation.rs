@@ -0,0 +1,185 @@
+use crate::board::Board;
+use crate::piece::Piece;
+use crate::piece_kind::*;
+use crate::piece_set::PieceSet;
+
+// ARS tries the plain rotation, then one cell right, then one cell left
+const ARS_KICKS: [(i32, i32); 3] = [(0,0),(1,0),(-1,0)];
+const NO_KICKS: [(i32, i32); 1] = [(0,0)];
+// Top row of the ARS 3x3 box
+const ARS_TOP_ROW: i32 = -1;
+
+// How pieces are oriented and kicked when rotating. Custom pieces keep
+// their own cells in every system, only the kicks change.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RotationSystem {
+    // Guideline Super Rotation System
+    Srs,
+    // Arika (TGM): states rest on the bottom of a 3x3 box, one kick each way
+    Ars,
+    // NES: right-handed S/Z/I, no kicks at all
+    Nes,
+}
+impl RotationSystem {
+    pub const ALL: [RotationSystem; 3] = [RotationSystem::Srs, RotationSystem::Ars, RotationSystem::Nes];
+    pub fn name(&self) -> &'static str {
+        match self {
+            RotationSystem::Srs => "SRS",
+            RotationSystem::Ars => "ARS",
+            RotationSystem::Nes => "NES",
+        }
+    }
+    // Cell offsets for an orientation, same layout as PieceKind::cells
+    pub fn cells<'a>(&self, pieces: &'a PieceSet, kind: PieceKind, orientation: u8) -> &'a [(i32,i32)] {
+        let o = orientation as usize & 3;
+        match (self, kind) {
+            (_, PieceKind::None | PieceKind::Custom(_)) | (RotationSystem::Srs, _) => pieces.cells(kind, orientation),
+            (RotationSystem::Ars, _) => &ars_rotations(kind)[o],
+            (RotationSystem::Nes, _) => &nes_rotations(kind)[o],
+        }
+    }
+    // Added to the board's spawn point on top of the kind's own offset.
+    // ARS and NES states sit one row lower in their box than SRS ones.
+    pub fn spawn_offset(&self, pieces: &PieceSet, kind: PieceKind) -> (i32, i32) {
+        let (dcol, drow) = pieces.spawn_offset(kind);
+        match (self, kind) {
+            (_, PieceKind::Custom(_)) | (RotationSystem::Srs, _) => (dcol, drow),
+            (RotationSystem::Ars, _) => (dcol, drow - 1),
+            (RotationSystem::Nes, _) => (dcol + 1, drow - 1),
+        }
+    }
+    // Kick offsets to try, in order, when `piece` rotates to `to`
+    pub fn kicks(&self, piece: &Piece, to: u8, board: &Board) -> &'static [(i32,i32)] {
+        match self {
+            RotationSystem::Srs => piece.pieces.kick_table(piece.kind).kicks(piece.orientation, to),
+            RotationSystem::Nes => &NO_KICKS,
+            RotationSystem::Ars => match piece.kind {
+                PieceKind::I | PieceKind::O | PieceKind::None => &NO_KICKS,
+                PieceKind::L | PieceKind::J | PieceKind::T
+                    if self.center_column_blocked(piece, to, board) => &NO_KICKS,
+                _ => &ARS_KICKS,
+            },
+        }
+    }
+    // ARS centre column rule: look at the cells of the new state that are
+    // blocked, in reading order. If the first one is in the middle column
+    // of the box the rotation may not kick. L and J are let off when that
+    // cell is the top of the column and the new state hooks over beside it.
+    fn center_column_blocked(&self, piece: &Piece, to: u8, board: &Board) -> bool {
+        let mut cells = self.cells(&piece.pieces, piece.kind, to).to_vec();
+        cells.sort_by_key(|&(dc, dr)| (dr, dc));
+        let first = cells.iter().find(|&&(dc, dr)| {
+            let (c, r) = (piece.col + dc, piece.row + dr);
+            !board.in_bounds(c, r) || board.cell_filled(c, r)
+        });
+        match first {
+            Some(&(0, dr)) => {
+                let hooked = matches!(piece.kind, PieceKind::L | PieceKind::J)
+                    && dr == ARS_TOP_ROW
+                    && cells.iter().any(|&(dc, r)| r == ARS_TOP_ROW && dc != 0);
+                !hooked
+            },
+            _ => false,
+        }
+    }
+}
+
+// ARS states, bottom aligned in a 3x3 box spanning rows -1..=1. T, L and J
+// spawn flat side up; I, S and Z only have two states.
+const fn ars_rotations(kind: PieceKind) -> &'static [[(i32,i32);4]; 4] {
+    match kind {
+        PieceKind::O => &[
+            [(0,0),(1,0),(0,1),(1,1)],
+            [(0,0),(1,0),(0,1),(1,1)],
+            [(0,0),(1,0),(0,1),(1,1)],
+            [(0,0),(1,0),(0,1),(1,1)],
+        ],
+        PieceKind::I => &[
+            [(-1,0),(0,0),(1,0),(2,0)],
+            [(1,-1),(1,0),(1,1),(1,2)],
+            [(-1,0),(0,0),(1,0),(2,0)],
+            [(1,-1),(1,0),(1,1),(1,2)],
+        ],
+        PieceKind::T => &[
+            [(-1,0),(0,0),(1,0),(0,1)],
+            [(0,-1),(0,0),(0,1),(-1,0)],
+            [(0,0),(-1,1),(0,1),(1,1)],
+            [(0,-1),(0,0),(0,1),(1,0)],
+        ],
+        PieceKind::L => &[
+            [(-1,0),(0,0),(1,0),(-1,1)],
+            [(-1,-1),(0,-1),(0,0),(0,1)],
+            [(1,0),(-1,1),(0,1),(1,1)],
+            [(0,-1),(0,0),(0,1),(1,1)],
+        ],
+        PieceKind::J => &[
+            [(-1,0),(0,0),(1,0),(1,1)],
+            [(0,-1),(0,0),(0,1),(-1,1)],
+            [(-1,0),(-1,1),(0,1),(1,1)],
+            [(0,-1),(1,-1),(0,0),(0,1)],
+        ],
+        PieceKind::Z => &[
+            [(-1,0),(0,0),(0,1),(1,1)],
+            [(1,-1),(0,0),(1,0),(0,1)],
+            [(-1,0),(0,0),(0,1),(1,1)],
+            [(1,-1),(0,0),(1,0),(0,1)],
+        ],
+        PieceKind::S => &[
+            [(0,0),(1,0),(-1,1),(0,1)],
+            [(-1,-1),(-1,0),(0,0),(0,1)],
+            [(0,0),(1,0),(-1,1),(0,1)],
+            [(-1,-1),(-1,0),(0,0),(0,1)],
+        ],
+        PieceKind::None | PieceKind::Custom(_) => &[[(0,0);4]; 4],
+    }
+}
+
+// NES states. T, L and J turn about (0,0) and spawn flat side up; the
+// vertical I, S and Z states lean to the right of the centre.
+const fn nes_rotations(kind: PieceKind) -> &'static [[(i32,i32);4]; 4] {
+    match kind {
+        PieceKind::O => &[
+            [(-1,0),(0,0),(-1,1),(0,1)],
+            [(-1,0),(0,0),(-1,1),(0,1)],
+            [(-1,0),(0,0),(-1,1),(0,1)],
+            [(-1,0),(0,0),(-1,1),(0,1)],
+        ],
+        PieceKind::I => &[
+            [(-2,0),(-1,0),(0,0),(1,0)],
+            [(0,-2),(0,-1),(0,0),(0,1)],
+            [(-2,0),(-1,0),(0,0),(1,0)],
+            [(0,-2),(0,-1),(0,0),(0,1)],
+        ],
+        PieceKind::T => &[
+            [(-1,0),(0,0),(1,0),(0,1)],
+            [(0,-1),(0,0),(0,1),(-1,0)],
+            [(1,0),(0,0),(-1,0),(0,-1)],
+            [(0,1),(0,0),(0,-1),(1,0)],
+        ],
+        PieceKind::L => &[
+            [(-1,0),(0,0),(1,0),(-1,1)],
+            [(0,-1),(0,0),(0,1),(-1,-1)],
+            [(1,0),(0,0),(-1,0),(1,-1)],
+            [(0,1),(0,0),(0,-1),(1,1)],
+        ],
+        PieceKind::J => &[
+            [(-1,0),(0,0),(1,0),(1,1)],
+            [(0,-1),(0,0),(0,1),(-1,1)],
+            [(1,0),(0,0),(-1,0),(-1,-1)],
+            [(0,1),(0,0),(0,-1),(1,-1)],
+        ],
+        PieceKind::Z => &[
+            [(-1,0),(0,0),(0,1),(1,1)],
+            [(1,-1),(0,0),(1,0),(0,1)],
+            [(-1,0),(0,0),(0,1),(1,1)],
+            [(1,-1),(0,0),(1,0),(0,1)],
+        ],
+        PieceKind::S => &[
+            [(0,0),(1,0),(-1,1),(0,1)],
+            [(0,-1),(0,0),(1,0),(1,1)],
+            [(0,0),(1,0),(-1,1),(0,1)],
+            [(0,-1),(0,0),(1,0),(1,1)],
+        ],
+        PieceKind::None | PieceKind::Custom(_) => &[[(0,0);4]; 4],
+    }
+}
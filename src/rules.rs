@@ -0,0 +1,182 @@
+use crate::randomizer::RandomizerKind;
+use crate::gravity::GravityCurve;
+use crate::constants::*;
+use crate::piece_set::PieceSetKind;
+use crate::mode::step;
+use crate::rotation::RotationSystem;
+use crate::scoring::ScoringKind;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LockDelayPolicy {
+    // Moves and rotations on the ground restart the timer, at most
+    // `max_resets` times per lowest row reached
+    MoveReset { max_resets: u8 },
+    // Only falling a row restarts the timer
+    StepReset,
+    // The timer runs down whenever the piece is grounded
+    NoReset,
+    // Never locks by itself, only by hard drop
+    Infinite,
+}
+impl LockDelayPolicy {
+    pub const ALL: [LockDelayPolicy; 4] = [
+        LockDelayPolicy::MoveReset { max_resets: PRE_LOCK_MOVES_ALLOWED },
+        LockDelayPolicy::StepReset,
+        LockDelayPolicy::NoReset,
+        LockDelayPolicy::Infinite,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            LockDelayPolicy::MoveReset { .. } => "Move reset",
+            LockDelayPolicy::StepReset        => "Step reset",
+            LockDelayPolicy::NoReset          => "No reset",
+            LockDelayPolicy::Infinite         => "Infinite",
+        }
+    }
+}
+
+// Rule set presets in the order the start menu cycles through them
+const PRESETS: [&str; 3] = ["Guideline", "Classic", "Arika"];
+
+// Rules that differ between game modes / house rules.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RuleSet {
+    // Visible matrix size, the vanish zone is added on top
+    pub board_width: usize,
+    pub board_height: usize,
+    pub pieces: PieceSetKind,
+    // Piece orientations and wall kicks
+    pub rotation: RotationSystem,
+    pub randomizer: RandomizerKind,
+    // Fixed seed for reproducible piece sequences. None picks one at start.
+    pub seed: Option<u64>,
+    pub hold_enabled: bool,
+    pub rotate_180_enabled: bool,
+    // Reward S/Z/L/J/I pieces rotated into a spot they can't move out of
+    pub all_spin: bool,
+    // Number of upcoming pieces shown, 1 to MAX_PREVIEW_COUNT
+    pub preview_count: usize,
+    pub gravity: GravityCurve,
+    pub scoring: ScoringKind,
+    pub lock_delay: LockDelayPolicy,
+    pub lock_delay_ms: f32,
+    // Top out when a piece locks even partly in the vanish zone
+    pub partial_lock_out: bool,
+    // Entry delay between a lock and the next spawn, and after a line clear
+    pub are_ms: f32,
+    pub line_clear_are_ms: f32,
+}
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::guideline()
+    }
+}
+impl RuleSet {
+    pub fn guideline() -> Self {
+        Self {
+            board_width: GRID_W,
+            board_height: GRID_H,
+            pieces: PieceSetKind::Standard,
+            rotation: RotationSystem::Srs,
+            randomizer: RandomizerKind::SevenBag,
+            seed: None,
+            hold_enabled: true,
+            rotate_180_enabled: true,
+            all_spin: false,
+            preview_count: 5,
+            gravity: GravityCurve::Guideline,
+            scoring: ScoringKind::Guideline,
+            lock_delay: LockDelayPolicy::MoveReset { max_resets: PRE_LOCK_MOVES_ALLOWED },
+            lock_delay_ms: LOCK_DELTA_THRESHOLD,
+            partial_lock_out: false,
+            are_ms: 100.0,
+            line_clear_are_ms: 100.0,
+        }
+    }
+    pub fn classic() -> Self {
+        Self {
+            board_width: GRID_W,
+            board_height: GRID_H,
+            pieces: PieceSetKind::Standard,
+            rotation: RotationSystem::Nes,
+            randomizer: RandomizerKind::Uniform,
+            seed: None,
+            hold_enabled: false,
+            rotate_180_enabled: false,
+            all_spin: false,
+            preview_count: 1,
+            gravity: GravityCurve::Nes,
+            scoring: ScoringKind::Nes,
+            lock_delay: LockDelayPolicy::NoReset,
+            lock_delay_ms: LOCK_DELTA_THRESHOLD,
+            partial_lock_out: false,
+            are_ms: 167.0,
+            line_clear_are_ms: 167.0,
+        }
+    }
+    // TGM style: ARS rotation, a history randomizer and step reset
+    pub fn arika() -> Self {
+        Self {
+            board_width: GRID_W,
+            board_height: GRID_H,
+            pieces: PieceSetKind::Standard,
+            rotation: RotationSystem::Ars,
+            randomizer: RandomizerKind::Tgm,
+            seed: None,
+            hold_enabled: true,
+            rotate_180_enabled: false,
+            all_spin: false,
+            preview_count: 3,
+            gravity: GravityCurve::Guideline,
+            scoring: ScoringKind::Tgm,
+            lock_delay: LockDelayPolicy::StepReset,
+            lock_delay_ms: LOCK_DELTA_THRESHOLD,
+            partial_lock_out: false,
+            are_ms: 450.0,
+            line_clear_are_ms: 450.0,
+        }
+    }
+    // One of PRESETS
+    pub fn preset(name: &str) -> Self {
+        match name {
+            "Classic" => Self::classic(),
+            "Arika" => Self::arika(),
+            _ => Self::guideline(),
+        }
+    }
+    // The rules the player can pick on the start menu, as (label, value)
+    pub fn options(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Rules", self.name().to_string()),
+            ("Rotation", self.rotation.name().to_string()),
+            ("Randomizer", self.randomizer.name().to_string()),
+            ("Scoring", self.scoring.name().to_string()),
+            ("Gravity", self.gravity.name()),
+            ("Lock delay", self.lock_delay.name().to_string()),
+            ("All-spin", if self.all_spin { "On" } else { "Off" }.to_string()),
+            ("Pieces", self.pieces.name().to_string()),
+        ]
+    }
+    // Step option `idx` of options(). A preset replaces every rule but the
+    // piece set.
+    pub fn adjust(&self, idx: usize, dir: i32) -> RuleSet {
+        let mut rules = self.clone();
+        match idx {
+            0 => rules = RuleSet { pieces: self.pieces, ..Self::preset(step(&PRESETS, self.name(), dir)) },
+            1 => rules.rotation = step(&RotationSystem::ALL, self.rotation, dir),
+            2 => rules.randomizer = step(&RandomizerKind::ALL, self.randomizer, dir),
+            3 => rules.scoring = step(&ScoringKind::ALL, self.scoring, dir),
+            4 => rules.gravity = step(&GravityCurve::ALL, self.gravity, dir),
+            5 => rules.lock_delay = step(&LockDelayPolicy::ALL, self.lock_delay, dir),
+            6 => rules.all_spin = !self.all_spin,
+            _ => rules.pieces = step(&PieceSetKind::ALL, self.pieces, dir),
+        }
+        rules
+    }
+    // The preset these rules match, piece set and seed aside, or "Custom"
+    pub fn name(&self) -> &'static str {
+        PRESETS.iter().copied()
+            .find(|&name| RuleSet { pieces: self.pieces, seed: self.seed, ..Self::preset(name) } == *self)
+            .unwrap_or("Custom")
+    }
+}
@@ -0,0 +1,265 @@
+use crate::game::{ClearEvent, ClearResult};
+
+// NES line clear points at level 0
+const NES_LINE_POINTS: [i32; 5] = [0, 40, 100, 300, 1200];
+// TGM's level counter tops out here
+const TGM_MAX_LEVEL: i32 = 999;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ScoringKind {
+    Guideline,
+    Nes,
+    Tgm,
+}
+impl ScoringKind {
+    pub const ALL: [ScoringKind; 3] = [ScoringKind::Guideline, ScoringKind::Nes, ScoringKind::Tgm];
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScoringKind::Guideline => "Guideline",
+            ScoringKind::Nes       => "NES",
+            ScoringKind::Tgm       => "TGM",
+        }
+    }
+    pub fn build(&self) -> Box<dyn ScoringSystem> {
+        match self {
+            ScoringKind::Guideline => Box::new(GuidelineScoring),
+            ScoringKind::Nes       => Box::new(NesScoring),
+            ScoringKind::Tgm       => Box::new(TgmScoring::default()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DropKind {
+    Soft,
+    Sonic,
+    Hard,
+}
+
+// Turns what the player did into points. `level` is the game's level,
+// which starts at 1.
+pub trait ScoringSystem {
+    // A piece was moved `rows` down by the player
+    fn dropped(&mut self, kind: DropKind, rows: i32) -> i32;
+    // A piece locked. Called for every lock, cleared lines or not; the
+    // event's points are not filled in yet.
+    fn lock(&mut self, clear: &ClearEvent, level: i32) -> i32;
+}
+
+// ======================================
+// Guideline: T-spins, back-to-back, combos and perfect clears
+// ======================================
+pub struct GuidelineScoring;
+impl ScoringSystem for GuidelineScoring {
+    fn dropped(&mut self, kind: DropKind, rows: i32) -> i32 {
+        match kind {
+            DropKind::Soft | DropKind::Sonic => rows,
+            DropKind::Hard => 2 * rows,
+        }
+    }
+    fn lock(&mut self, clear: &ClearEvent, level: i32) -> i32 {
+        let base_points = match clear.result {
+            ClearResult::Single          => 100,
+            ClearResult::Double          => 300,
+            ClearResult::Triple          => 500,
+            ClearResult::Tetris          => 800,
+            ClearResult::TSpin           => 400,
+            ClearResult::TSpinMini       => 100,
+            ClearResult::TSpinMiniSingle => 200,
+            ClearResult::TSpinMiniDouble => 400,
+            ClearResult::TSpinSingle     => 800,
+            ClearResult::TSpinDouble     => 1200,
+            ClearResult::TSpinTriple     => 1600,
+            // all-spins score like minis, a triple a little over a plain one
+            ClearResult::Spin            => 100,
+            ClearResult::SpinSingle      => 200,
+            ClearResult::SpinDouble      => 400,
+            ClearResult::SpinTriple      => 600,
+            ClearResult::None            => 0,
+        };
+        let mut score = base_points * level;
+        // Tetris or spin line clear following another one: +50%
+        if clear.back_to_back {
+            score += score / 2;
+        }
+        // Combo: +50 x combo x level for every clear after the first in a row
+        if clear.lines > 0 && clear.combo > 0 {
+            score += 50 * clear.combo * level;
+        }
+        // Perfect clear: bonus on top of the clear itself
+        if clear.perfect_clear {
+            let bonus = match clear.lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if clear.back_to_back => 3200,
+                _ => 2000,
+            };
+            score += bonus * level;
+        }
+        score
+    }
+}
+
+// ======================================
+// NES: 40/100/300/1200 x (NES level + 1), soft drop 1 per row
+// ======================================
+pub struct NesScoring;
+impl ScoringSystem for NesScoring {
+    fn dropped(&mut self, kind: DropKind, rows: i32) -> i32 {
+        match kind {
+            DropKind::Soft => rows,
+            // the NES had no hard or sonic drop
+            DropKind::Sonic | DropKind::Hard => 0,
+        }
+    }
+    fn lock(&mut self, clear: &ClearEvent, level: i32) -> i32 {
+        // game level 1 is NES level 0, so the game level is the multiplier
+        let lines = clear.lines.clamp(0, 4) as usize;
+        NES_LINE_POINTS[lines] * level
+    }
+}
+
+// ======================================
+// TGM: (ceil((level + lines) / 4) + soft) x lines x combo x bravo
+// ======================================
+pub struct TgmScoring {
+    // TGM's own 0-999 level, which the game's level doesn't follow: +1 per
+    // piece, stopping at each x99 until a line is cleared, + lines cleared
+    level: i32,
+    // starts at 1, grows by 2 x lines - 2 per clear, reset by a lock
+    // that clears nothing
+    combo: i32,
+    // rows dropped by hand with the current piece
+    soft: i32,
+}
+impl Default for TgmScoring {
+    fn default() -> Self {
+        Self { level: 0, combo: 1, soft: 0 }
+    }
+}
+impl TgmScoring {
+    // Counts the lock towards the level, for the next piece
+    fn advance_level(&mut self, lines: i32) {
+        self.level = (self.level + lines).min(TGM_MAX_LEVEL);
+        let section_stop = self.level % 100 == 99 || self.level == TGM_MAX_LEVEL - 1;
+        if !section_stop && self.level < TGM_MAX_LEVEL {
+            self.level += 1;
+        }
+    }
+}
+impl ScoringSystem for TgmScoring {
+    fn dropped(&mut self, kind: DropKind, rows: i32) -> i32 {
+        // sonic drop doesn't count, and the points only come with a clear
+        if kind != DropKind::Sonic {
+            self.soft += rows;
+        }
+        0
+    }
+    // Scores with its own level rather than the game's
+    fn lock(&mut self, clear: &ClearEvent, _level: i32) -> i32 {
+        let soft = std::mem::take(&mut self.soft);
+        let level = self.level;
+        self.advance_level(clear.lines);
+        if clear.lines == 0 {
+            self.combo = 1;
+            return 0;
+        }
+        self.combo += 2 * clear.lines - 2;
+        let bravo = if clear.perfect_clear { 4 } else { 1 };
+        ((level + clear.lines + 3) / 4 + soft) * clear.lines * self.combo * bravo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(result: ClearResult, lines: i32) -> ClearEvent {
+        ClearEvent { result, lines, ..ClearEvent::default() }
+    }
+
+    #[test]
+    fn guideline_line_clears_and_spins() {
+        let mut scoring = GuidelineScoring;
+        assert_eq!(scoring.lock(&clear(ClearResult::Single, 1), 1), 100);
+        assert_eq!(scoring.lock(&clear(ClearResult::Tetris, 4), 2), 1600);
+        assert_eq!(scoring.lock(&clear(ClearResult::TSpinDouble, 2), 3), 3600);
+        assert_eq!(scoring.lock(&clear(ClearResult::TSpinMini, 0), 1), 100);
+        assert_eq!(scoring.lock(&clear(ClearResult::None, 0), 5), 0);
+    }
+
+    #[test]
+    fn guideline_bonuses() {
+        let mut scoring = GuidelineScoring;
+        let b2b = ClearEvent { back_to_back: true, ..clear(ClearResult::Tetris, 4) };
+        assert_eq!(scoring.lock(&b2b, 1), 1200);
+        let combo = ClearEvent { combo: 3, ..clear(ClearResult::Single, 1) };
+        assert_eq!(scoring.lock(&combo, 2), 200 + 300);
+        // a combo count on a lock that clears nothing scores nothing
+        let broken = ClearEvent { combo: 3, ..clear(ClearResult::None, 0) };
+        assert_eq!(scoring.lock(&broken, 1), 0);
+        let perfect = ClearEvent { perfect_clear: true, ..clear(ClearResult::Double, 2) };
+        assert_eq!(scoring.lock(&perfect, 1), 300 + 1200);
+        let b2b_perfect = ClearEvent { perfect_clear: true, ..b2b };
+        assert_eq!(scoring.lock(&b2b_perfect, 1), 1200 + 3200);
+    }
+
+    #[test]
+    fn guideline_drops() {
+        let mut scoring = GuidelineScoring;
+        assert_eq!(scoring.dropped(DropKind::Soft, 5), 5);
+        assert_eq!(scoring.dropped(DropKind::Sonic, 5), 5);
+        assert_eq!(scoring.dropped(DropKind::Hard, 5), 10);
+    }
+
+    #[test]
+    fn nes_scoring() {
+        let mut scoring = NesScoring;
+        assert_eq!(scoring.lock(&clear(ClearResult::Single, 1), 1), 40);
+        assert_eq!(scoring.lock(&clear(ClearResult::Double, 2), 3), 300);
+        assert_eq!(scoring.lock(&clear(ClearResult::Tetris, 4), 10), 12000);
+        // spins are worth nothing extra
+        assert_eq!(scoring.lock(&clear(ClearResult::TSpinTriple, 3), 1), 300);
+        assert_eq!(scoring.dropped(DropKind::Soft, 7), 7);
+        assert_eq!(scoring.dropped(DropKind::Hard, 7), 0);
+    }
+
+    #[test]
+    fn tgm_scoring() {
+        let mut scoring = TgmScoring::default();
+        // soft drop is paid out with the clear, sonic drop isn't counted
+        assert_eq!(scoring.dropped(DropKind::Soft, 5), 0);
+        assert_eq!(scoring.dropped(DropKind::Sonic, 5), 0);
+        // level 0: (ceil(1/4) + 5) x 1 x combo 1
+        assert_eq!(scoring.lock(&clear(ClearResult::Single, 1), 1), 6);
+        assert_eq!(scoring.level, 2);
+        // level 2: ceil(4/4) x 2 x combo 3
+        assert_eq!(scoring.lock(&clear(ClearResult::Double, 2), 1), 6);
+        assert_eq!(scoring.combo, 3);
+        assert_eq!(scoring.lock(&clear(ClearResult::None, 0), 1), 0);
+        assert_eq!(scoring.combo, 1);
+        // level 6: ceil(10/4) x 4 x combo 7 x bravo
+        let perfect = ClearEvent { perfect_clear: true, ..clear(ClearResult::Tetris, 4) };
+        assert_eq!(scoring.level, 6);
+        assert_eq!(scoring.lock(&perfect, 1), 3 * 4 * 7 * 4);
+    }
+
+    #[test]
+    fn tgm_level_stops_at_sections() {
+        let mut scoring = TgmScoring { level: 98, ..TgmScoring::default() };
+        scoring.advance_level(0);
+        assert_eq!(scoring.level, 99);
+        scoring.advance_level(0);
+        assert_eq!(scoring.level, 99);
+        scoring.advance_level(1);
+        assert_eq!(scoring.level, 101);
+        let mut scoring = TgmScoring { level: 997, ..TgmScoring::default() };
+        scoring.advance_level(0);
+        assert_eq!(scoring.level, 998);
+        scoring.advance_level(0);
+        assert_eq!(scoring.level, 998);
+        scoring.advance_level(4);
+        assert_eq!(scoring.level, TGM_MAX_LEVEL);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::mode::step;
+use crate::storage;
+
+const STORAGE_KEY: &str = "wasm_tetris_settings";
+// Handling steps on the settings screen, in ms. 0 ARR and DAS cut are off.
+const DAS_OPTIONS: [f32; 8] = [50.0, 83.0, 100.0, 117.0, 133.0, 150.0, 167.0, 200.0];
+const ARR_OPTIONS: [f32; 7] = [0.0, 17.0, 33.0, 50.0, 67.0, 83.0, 100.0];
+const DAS_CUT_OPTIONS: [f32; 5] = [0.0, 17.0, 33.0, 50.0, 100.0];
+const SOFT_DROP_OPTIONS: [f32; 5] = [5.0, 10.0, 20.0, 40.0, f32::INFINITY];
+
+// Per-player preferences. Unlike RuleSet these never change how a game
+// plays out, only how it is presented and controlled.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub show_ghost: bool,
+    // Delayed Auto Shift: how long a direction is held before it repeats
+    pub das_ms: f32,
+    // Auto Repeat Rate: time between repeats once DAS is charged, 0 = instant
+    pub arr_ms: f32,
+    // DAS cut: pause auto repeat for this long after a rotation
+    pub das_cut_ms: f32,
+    // Soft drop speed as a multiple of gravity, f32::INFINITY = instant
+    pub soft_drop_factor: f32,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            show_ghost: true,
+            das_ms: 167.0,
+            arr_ms: 33.0,
+            das_cut_ms: 0.0,
+            soft_drop_factor: 20.0,
+        }
+    }
+}
+impl Settings {
+    // The saved settings, defaults for anything not saved
+    pub fn load() -> Self {
+        storage::load(STORAGE_KEY).map(|src| Self::parse(&src)).unwrap_or_default()
+    }
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, &self.serialize());
+    }
+    // For the settings screen, as (label, value)
+    pub fn options(&self) -> Vec<(&'static str, String)> {
+        let soft_drop = if self.soft_drop_factor.is_infinite() {
+            "Instant".to_string()
+        } else {
+            format!("{}x", self.soft_drop_factor)
+        };
+        vec![
+            ("Ghost piece", if self.show_ghost { "On" } else { "Off" }.to_string()),
+            ("DAS", format!("{} ms", self.das_ms)),
+            ("ARR", format!("{} ms", self.arr_ms)),
+            ("DAS cut", format!("{} ms", self.das_cut_ms)),
+            ("Soft drop", soft_drop),
+        ]
+    }
+    // Step option `idx` of options()
+    pub fn adjust(&self, idx: usize, dir: i32) -> Settings {
+        let mut settings = self.clone();
+        match idx {
+            0 => settings.show_ghost = !self.show_ghost,
+            1 => settings.das_ms = step(&DAS_OPTIONS, self.das_ms, dir),
+            2 => settings.arr_ms = step(&ARR_OPTIONS, self.arr_ms, dir),
+            3 => settings.das_cut_ms = step(&DAS_CUT_OPTIONS, self.das_cut_ms, dir),
+            _ => settings.soft_drop_factor = step(&SOFT_DROP_OPTIONS, self.soft_drop_factor, dir),
+        }
+        settings
+    }
+    // One `<name> <value>` per line. Unknown names and bad values are
+    // skipped, leaving the default.
+    fn parse(src: &str) -> Self {
+        let mut settings = Self::default();
+        for line in src.lines() {
+            let Some((name, value)) = line.split_once(' ') else {
+                continue;
+            };
+            let value = value.trim();
+            match name {
+                "show_ghost" => settings.show_ghost = value == "true",
+                "das_ms" => settings.das_ms = value.parse().unwrap_or(settings.das_ms),
+                "arr_ms" => settings.arr_ms = value.parse().unwrap_or(settings.arr_ms),
+                "das_cut_ms" => settings.das_cut_ms = value.parse().unwrap_or(settings.das_cut_ms),
+                "soft_drop_factor" => {
+                    settings.soft_drop_factor = value.parse().unwrap_or(settings.soft_drop_factor)
+                },
+                _ => {},
+            }
+        }
+        settings
+    }
+    fn serialize(&self) -> String {
+        format!(
+            "show_ghost {}\ndas_ms {}\narr_ms {}\ndas_cut_ms {}\nsoft_drop_factor {}\n",
+            self.show_ghost, self.das_ms, self.arr_ms, self.das_cut_ms, self.soft_drop_factor,
+        )
+    }
+}
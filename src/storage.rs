@@ -0,0 +1,43 @@
+// Small key/value store that outlives the program: the browser's
+// localStorage on the web (see the plugin in index.html), a file per key
+// in the working directory natively.
+pub use backend::{load, save};
+
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    unsafe extern "C" {
+        // byte length of the stored value, -1 if there is none
+        fn storage_len(key: *const u8, key_len: usize) -> i32;
+        fn storage_read(key: *const u8, key_len: usize, buf: *mut u8, buf_len: usize);
+        fn storage_write(key: *const u8, key_len: usize, value: *const u8, value_len: usize);
+    }
+    pub fn load(key: &str) -> Option<String> {
+        let len = unsafe { storage_len(key.as_ptr(), key.len()) };
+        if len < 0 {
+            return None;
+        }
+        let mut buf = vec![0u8; len as usize];
+        unsafe { storage_read(key.as_ptr(), key.len(), buf.as_mut_ptr(), buf.len()) };
+        String::from_utf8(buf).ok()
+    }
+    pub fn save(key: &str, value: &str) {
+        unsafe { storage_write(key.as_ptr(), key.len(), value.as_ptr(), value.len()) };
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use macroquad::prelude::*;
+
+    fn path(key: &str) -> String {
+        format!("{}.sav", key)
+    }
+    pub fn load(key: &str) -> Option<String> {
+        std::fs::read_to_string(path(key)).ok()
+    }
+    pub fn save(key: &str, value: &str) {
+        if let Err(e) = std::fs::write(path(key), value) {
+            warn!("saving {}: {}", key, e);
+        }
+    }
+}